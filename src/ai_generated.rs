@@ -0,0 +1,149 @@
+// src/ai_generated.rs
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::llm::LlmProvider;
+use crate::prompts::ResolvedPrompt;
+
+/// Context files larger than this are cut, so one log file cannot crowd out the prompt.
+const MAX_CONTEXT_FILE_BYTES: usize = 64 * 1024;
+const MAX_CONTEXT_BYTES: usize = 256 * 1024;
+
+const SYSTEM_INSTRUCTIONS: &str = "You write one file of a project scaffolded by forge. \
+Reply with the complete contents of the file only, without explanations or surrounding code fences.";
+
+/// A file in `forge-template.json`'s `ai_generated`, written by an LLM after
+/// the templates are rendered.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AiGeneratedFile {
+    /// Output path relative to the output directory. If a template renders to
+    /// the same path, its output is given to the model as a draft.
+    pub path: String,
+    /// What to write. Either this or `uses`.
+    pub prompt: Option<String>,
+    /// A prompt asset, `name` or `name@<semver range>`.
+    pub uses: Option<String>,
+    /// Globs, relative to the output directory, of rendered files to include as context.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+}
+
+impl AiGeneratedFile {
+    /// Problems that make the entry unusable.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !stays_inside(&self.path) {
+            problems.push(format!("path '{}' must be relative and stay inside the output directory", self.path));
+        }
+        match (&self.prompt, &self.uses) {
+            (Some(_), Some(_)) => problems.push("set either prompt or uses, not both".to_string()),
+            (None, None) => problems.push("set prompt or uses".to_string()),
+            (Some(prompt), None) if prompt.trim().is_empty() => problems.push("prompt must not be empty".to_string()),
+            _ => {}
+        }
+        for pattern in &self.context {
+            if !stays_inside(pattern) {
+                problems.push(format!("context glob '{}' must be relative and stay inside the output directory", pattern));
+            } else if let Err(err) = glob::Pattern::new(pattern) {
+                problems.push(format!("context glob '{}' is invalid: {}", pattern, err));
+            }
+        }
+        problems
+    }
+}
+
+/// Is `path` relative and without `.` or `..` parts?
+fn stays_inside(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Write `file` into `output_dir` with `provider`, giving it the prompt's
+/// variables, the template's draft and the context files.
+pub fn generate_file(provider: &dyn LlmProvider, file: &AiGeneratedFile, prompt: &ResolvedPrompt, output_dir: &Path) -> Result<PathBuf> {
+    if let Some(problem) = file.problems().first() {
+        anyhow::bail!("Invalid ai_generated entry '{}': {}", file.path, problem);
+    }
+    let output_path = output_dir.join(&file.path);
+    let message = build_prompt(file, prompt, &output_path, output_dir)?;
+    let system = [Some(SYSTEM_INSTRUCTIONS.to_string()), prompt.system.clone(), prompt.output.instructions()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let reply = provider.complete(&system, &message)
+        .with_context(|| format!("Failed to generate {} with {}", file.path, provider.id()))?;
+
+    let content = strip_code_fence(&reply);
+    if let Err(err) = prompt.output.check(&content) {
+        anyhow::bail!("{} wrote {} in breach of prompt {}: {:#}",
+            provider.id(), file.path, prompt.asset.as_deref().unwrap_or("constraints"), err);
+    }
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&output_path, content)?;
+    Ok(output_path)
+}
+
+fn build_prompt(file: &AiGeneratedFile, prompt: &ResolvedPrompt, output_path: &Path, output_dir: &Path) -> Result<String> {
+    let mut message = format!("{}\n\nWrite the file `{}`.\n\nProject variables:\n```json\n{}\n```\n",
+        prompt.text.trim(), file.path, serde_json::to_string_pretty(&prompt.variables)?);
+
+    if let Ok(draft) = fs::read_to_string(output_path) && !draft.trim().is_empty() {
+        message.push_str(&format!("\nThe template's draft of `{}`:\n```\n{}\n```\n", file.path, draft.trim_end()));
+    }
+
+    let mut budget = MAX_CONTEXT_BYTES;
+    for (relative, content) in context_files(file, output_dir)? {
+        if budget == 0 {
+            break;
+        }
+        let mut end = content.len().min(MAX_CONTEXT_FILE_BYTES).min(budget);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        budget -= end;
+        let cut = if end < content.len() { "\n[... cut ...]" } else { "" };
+        message.push_str(&format!("\nContext file `{}`:\n```\n{}{}\n```\n", relative, content[..end].trim_end(), cut));
+    }
+    Ok(message)
+}
+
+/// Text files in the output directory matching the entry's context globs, by path.
+fn context_files(file: &AiGeneratedFile, output_dir: &Path) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    let root = output_dir.canonicalize()?;
+    for pattern in &file.context {
+        let full = output_dir.join(pattern);
+        for path in glob::glob(&full.to_string_lossy())?.filter_map(|p| p.ok()) {
+            let Ok(relative) = path.strip_prefix(output_dir) else { continue };
+            // A symlink must not lead the model out of the project
+            if !path.canonicalize().is_ok_and(|real| real.starts_with(&root)) {
+                continue;
+            }
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if relative == file.path || files.iter().any(|(seen, _)| seen == &relative) {
+                continue;
+            }
+            // Binary files are no use to the model
+            if let Ok(content) = fs::read_to_string(&path) {
+                files.push((relative, content));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Models often wrap a file in a code fence despite being asked not to.
+fn strip_code_fence(reply: &str) -> String {
+    let trimmed = reply.trim();
+    if let Some(inner) = trimmed.strip_prefix("```")
+        && let Some(inner) = inner.strip_suffix("```")
+        && let Some((_language, body)) = inner.split_once('\n') {
+        return format!("{}\n", body.trim_end());
+    }
+    format!("{}\n", trimmed)
+}
@@ -0,0 +1,367 @@
+// src/command_renderer.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+use crate::context::ForgeContext;
+use crate::lifecycle::variable_env_name;
+use crate::plugin::PluginMetadata;
+use crate::renderer::{Capabilities, Renderer, Requirement};
+use crate::sandbox::{sandboxed_command, SandboxSpec};
+
+pub const DEFAULT_RENDER_TIMEOUT_SECS: u64 = 300;
+
+/// How the template is handed to a renderer command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// Path to the template as an argument (`{input}`).
+    #[default]
+    File,
+    /// Template contents on stdin.
+    Stdin,
+}
+
+/// Where a renderer command puts its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// Writes to the path given as an argument (`{output}`).
+    #[default]
+    File,
+    /// Prints the rendered file on stdout.
+    Stdout,
+}
+
+/// How the template variables are handed to a renderer command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariablesMode {
+    /// Path to a JSON file as an argument (`{variables}`).
+    #[default]
+    File,
+    /// JSON on stdin.
+    Stdin,
+    /// `FORGE_VAR_<KEY>` environment variables.
+    Env,
+}
+
+/// An external renderer declared in a plugin's `renderers`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CustomRenderer {
+    /// Program and arguments. `{input}`, `{output}` and `{variables}` are
+    /// replaced by paths; file-mode paths without a placeholder are appended
+    /// in that order.
+    pub argv: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Working directory, relative to the plugin directory (the default).
+    pub cwd: Option<String>,
+    /// Extensions this renderer owns; empty means every extension without a built-in engine.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub input: InputMode,
+    #[serde(default)]
+    pub output: OutputMode,
+    #[serde(default)]
+    pub variables: VariablesMode,
+    pub timeout_secs: Option<u64>,
+}
+
+impl CustomRenderer {
+    /// The old `custom_renderer_command` string: `<runtime> <script> [args...]`,
+    /// called with input, output and variables paths appended.
+    pub fn from_legacy_command(command: &str) -> CustomRenderer {
+        CustomRenderer {
+            argv: command.split_whitespace().map(str::to_string).collect(),
+            env: BTreeMap::new(),
+            cwd: None,
+            extensions: Vec::new(),
+            input: InputMode::File,
+            output: OutputMode::File,
+            variables: VariablesMode::File,
+            timeout_secs: None,
+        }
+    }
+
+    pub fn is_catch_all(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// The command as shown for approval: environment overrides, then argv.
+    pub fn display(&self) -> String {
+        self.env.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .chain(self.argv.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Problems that make the declaration unusable.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.argv.is_empty() || self.argv[0].trim().is_empty() {
+            problems.push("argv must name a program".to_string());
+        }
+        if self.input == InputMode::Stdin && self.variables == VariablesMode::Stdin {
+            problems.push("input and variables cannot both use stdin".to_string());
+        }
+        if self.timeout_secs == Some(0) {
+            problems.push("timeout_secs must be greater than 0".to_string());
+        }
+        problems
+    }
+
+    /// Render `input_path` into `output_path` by running the command, sandboxed
+    /// when the plugin's trust level asks for it.
+    pub fn render(&self, ctx: &ForgeContext, plugin: &PluginMetadata, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        if let Some(problem) = self.problems().first() {
+            anyhow::bail!("Invalid renderer '{}': {}", self.display(), problem);
+        }
+        let timeout = Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_RENDER_TIMEOUT_SECS));
+        let input_path = std::path::absolute(input_path)?;
+        let output_path = std::path::absolute(output_path)?;
+        // The output must exist to be bind-mounted writable in a sandbox
+        fs::write(&output_path, "")?;
+
+        let vars_json = serde_json::to_string(variables)?;
+        let mut vars_file = NamedTempFile::new()?;
+        vars_file.write_all(vars_json.as_bytes())?;
+        vars_file.flush()?;
+
+        let mut args: Vec<String> = self.argv[1..].iter()
+            .map(|arg| arg
+                .replace("{input}", &input_path.to_string_lossy())
+                .replace("{output}", &output_path.to_string_lossy())
+                .replace("{variables}", &vars_file.path().to_string_lossy()))
+            .collect();
+        let mentions = |placeholder: &str| self.argv.iter().any(|arg| arg.contains(placeholder));
+        if self.input == InputMode::File && !mentions("{input}") {
+            args.push(input_path.to_string_lossy().into_owned());
+        }
+        if self.output == OutputMode::File && !mentions("{output}") {
+            args.push(output_path.to_string_lossy().into_owned());
+        }
+        if self.variables == VariablesMode::File && !mentions("{variables}") {
+            args.push(vars_file.path().to_string_lossy().into_owned());
+        }
+
+        let mut read_only = vec![
+            input_path.parent().unwrap_or(&input_path).to_path_buf(),
+            vars_file.path().to_path_buf(),
+        ];
+        read_only.extend(self.argv.iter().map(PathBuf::from).filter(|p| p.is_absolute() && p.exists()));
+        let workdir = self.cwd.as_ref().map(PathBuf::from);
+        read_only.extend(workdir.clone());
+        let spec = SandboxSpec { read_only, writable: vec![output_path.clone()], workdir };
+
+        let program = &self.argv[0];
+        let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        let mut command = sandboxed_command(ctx, Some(plugin), program, &arg_refs, &spec)?;
+        command.envs(&self.env);
+        if self.variables == VariablesMode::Env
+            && let Some(obj) = variables.as_object() {
+            for (key, value) in obj {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                command.env(variable_env_name(key), value);
+            }
+        }
+        let stdin = match (self.input, self.variables) {
+            (InputMode::Stdin, _) => Some(fs::read(&input_path)?),
+            (_, VariablesMode::Stdin) => Some(vars_json.into_bytes()),
+            _ => None,
+        };
+        command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+        if self.output == OutputMode::Stdout {
+            command.stdout(Stdio::piped());
+        }
+
+        let started = Instant::now();
+        let mut child = command.spawn()
+            .with_context(|| format!("Failed to run renderer '{}'. Is {} installed?", self.display(), program))?;
+
+        // Feed stdin and drain stdout on threads so neither side blocks on a full pipe
+        let writer = match (stdin, child.stdin.take()) {
+            (Some(bytes), Some(mut pipe)) => Some(thread::spawn(move || pipe.write_all(&bytes))),
+            _ => None,
+        };
+        let reader = child.stdout.take().map(|mut pipe| thread::spawn(move || {
+            let mut captured = Vec::new();
+            pipe.read_to_end(&mut captured).map(|_| captured)
+        }));
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() > timeout {
+                child.kill().ok();
+                child.wait().ok();
+                if let Some(writer) = writer {
+                    writer.join().ok();
+                }
+                if let Some(reader) = reader {
+                    reader.join().ok();
+                }
+                anyhow::bail!("Renderer '{}' timed out after {}s on {:?}", self.display(), timeout.as_secs(), input_path);
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+        if let Some(writer) = writer {
+            // A renderer may legitimately exit without reading all of stdin
+            writer.join().ok();
+        }
+
+        if !status.success() {
+            anyhow::bail!("Renderer '{}' failed for {:?} with exit code {}", self.display(), input_path,
+                status.code().map(|c| c.to_string()).unwrap_or_else(|| "unknown".to_string()));
+        }
+        if let Some(reader) = reader {
+            let rendered = reader.join()
+                .map_err(|_| anyhow::anyhow!("Renderer output reader panicked"))?
+                .context("Failed to read renderer output")?;
+            fs::write(&output_path, rendered)?;
+        }
+        Ok(())
+    }
+}
+
+/// Engine for one of a plugin's `renderers`.
+pub struct CommandEngine<'a> {
+    ctx: &'a ForgeContext,
+    plugin: &'a PluginMetadata,
+    renderer: CustomRenderer,
+}
+
+impl<'a> CommandEngine<'a> {
+    pub fn new(ctx: &'a ForgeContext, plugin: &'a PluginMetadata, renderer: CustomRenderer) -> CommandEngine<'a> {
+        CommandEngine { ctx, plugin, renderer }
+    }
+}
+
+impl Renderer for CommandEngine<'_> {
+    fn name(&self) -> String {
+        // `python3 render/liquid.py` is best known by its script, `gotmpl` by its program
+        let shown = match self.renderer.argv.get(1) {
+            Some(script) if !script.starts_with('-') && !script.contains('{') => script.as_str(),
+            _ => self.renderer.argv.first().map(String::as_str).unwrap_or(""),
+        };
+        let shown = Path::new(shown).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| shown.to_string());
+        format!("plugin:{}", shown)
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        self.renderer.extensions.clone()
+    }
+
+    fn requirements(&self) -> Vec<Requirement> {
+        self.renderer.argv.first()
+            .map(|program| Requirement::Program {
+                name: program.clone(),
+                cwd: self.renderer.cwd.as_ref().map(PathBuf::from),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { runs_plugin_code: true, sandboxable: true, ..Capabilities::default() }
+    }
+
+    fn approval_command(&self) -> String {
+        self.renderer.display()
+    }
+
+    fn approval_dir(&self) -> Option<PathBuf> {
+        self.renderer.cwd.as_ref().map(PathBuf::from)
+    }
+
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        self.renderer.render(self.ctx, self.plugin, input_path, output_path, variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use crate::test_support::{plugin, tree};
+
+    fn renderer(fields: Value) -> CustomRenderer {
+        serde_json::from_value(fields).expect("valid renderer")
+    }
+
+    #[test]
+    fn problems_name_unusable_declarations() {
+        assert_eq!(renderer(json!({ "argv": [] })).problems(), vec!["argv must name a program"]);
+        assert_eq!(renderer(json!({ "argv": ["render"], "input": "stdin", "variables": "stdin", "timeout_secs": 0 })).problems(), vec![
+            "input and variables cannot both use stdin",
+            "timeout_secs must be greater than 0",
+        ]);
+        assert!(renderer(json!({ "argv": ["render"], "input": "stdin", "output": "stdout" })).problems().is_empty());
+    }
+
+    #[test]
+    fn name_prefers_the_script_over_the_runtime() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let web = plugin("web", json!({}));
+        let name = |argv: Value| CommandEngine::new(&ctx, &web, renderer(json!({ "argv": argv }))).name();
+        assert_eq!(name(json!(["python3", "render/liquid.py"])), "plugin:liquid.py");
+        assert_eq!(name(json!(["gotmpl", "--strict"])), "plugin:gotmpl");
+        assert_eq!(name(json!(["/usr/bin/gotmpl", "{input}"])), "plugin:gotmpl");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_mode_appends_input_output_and_variables_paths() {
+        let home = TempDir::new().unwrap();
+        let dir = tree(&[("page.tmpl", "hello ")]);
+        let output = dir.path().join("page");
+        let declared = renderer(json!({ "argv": ["sh", "-c", r#"cat "$1" "$3" > "$2""#, "sh"] }));
+        declared.render(&ForgeContext::for_tests(home.path()), &plugin("web", json!({})),
+            &dir.path().join("page.tmpl"), &output, &json!({ "name": "shop" })).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), r#"hello {"name":"shop"}"#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stdin_and_stdout_modes_pipe_the_template() {
+        let home = TempDir::new().unwrap();
+        let dir = tree(&[("page.tmpl", "hello ")]);
+        let output = dir.path().join("page");
+        let declared = renderer(json!({
+            "argv": ["sh", "-c", r#"cat; printf %s "$FORGE_VAR_NAME""#],
+            "input": "stdin",
+            "output": "stdout",
+            "variables": "env",
+        }));
+        declared.render(&ForgeContext::for_tests(home.path()), &plugin("web", json!({})),
+            &dir.path().join("page.tmpl"), &output, &json!({ "name": "shop" })).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), "hello shop");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_renderers_report_their_exit_code() {
+        let home = TempDir::new().unwrap();
+        let dir = tree(&[("page.tmpl", "")]);
+        let declared = renderer(json!({ "argv": ["sh", "-c", "exit 4"], "input": "stdin", "output": "stdout" }));
+        let err = declared.render(&ForgeContext::for_tests(home.path()), &plugin("web", json!({})),
+            &dir.path().join("page.tmpl"), &dir.path().join("page"), &json!({})).unwrap_err();
+        assert!(err.to_string().ends_with("with exit code 4"), "{}", err);
+    }
+}
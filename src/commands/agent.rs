@@ -0,0 +1,10 @@
+// src/commands/agent.rs
+use anyhow::Result;
+use crate::context::ForgeContext;
+use crate::render_agent::serve;
+
+/// `forge agent serve`: render templates for clients whose project sets
+/// `render_agent` to this machine.
+pub fn agent_serve(ctx: &ForgeContext, listen: &str) -> Result<()> {
+    serve(ctx, listen)
+}
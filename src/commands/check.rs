@@ -0,0 +1,352 @@
+// src/commands/check.rs
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::Serialize;
+use walkdir::WalkDir;
+use crate::context::ForgeContext;
+use crate::llm_cache::LLM_CACHE_DIR;
+use crate::manifest::ForgeManifest;
+use crate::plugin::plugin_commands::{detect_plugin, installed_plugin_problem, installed_plugins, load_plugin_metadata};
+use crate::plugin::PluginMetadata;
+use crate::registry::{cached_list, is_remote};
+use crate::render_agent::{RenderAgent, RenderPlan};
+use crate::renderer::EngineRegistry;
+use crate::runtime_checker::{assess, declared_runtimes, item, required_for, runtime_version, CheckItem, CheckStatus};
+use crate::template_manifest::{TemplateManifest, TEMPLATE_MANIFEST};
+use crate::trust::TrustStore;
+
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub plugin: Option<String>,
+    pub items: Vec<CheckItem>,
+}
+
+impl CheckReport {
+    fn count(&self, status: CheckStatus) -> usize {
+        self.items.iter().filter(|i| i.status == status).count()
+    }
+}
+
+/// Cached plugin lists older than this are reported as stale.
+const STALE_LIST_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// `forge check`: verify that the runtimes, packages and tools the project's
+/// templates need are installed, and that installed plugins are intact.
+pub fn run_check(ctx: &ForgeContext, plugin_name: Option<String>) -> Result<()> {
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let plugin = resolve_plugin(ctx, plugin_name, &cwd)?;
+
+    let mut items = Vec::new();
+    check_engines(ctx, plugin.as_ref(), &cwd, &mut items);
+    check_git(&mut items);
+    check_plugins(ctx, &mut items)?;
+    check_home(ctx, &mut items);
+    check_cache(ctx, &cwd, &mut items);
+
+    let report = CheckReport { plugin: plugin.as_ref().map(|p| format!("{} {}", p.name, p.version)), items };
+    ctx.output.emit(&report, |r, out| {
+        match &r.plugin {
+            Some(plugin) => writeln!(out, "Checking forge environment for plugin {}", plugin)?,
+            None => writeln!(out, "Checking forge environment (no plugin selected; checking every built-in engine)")?,
+        }
+        for item in &r.items {
+            let mark = match item.status {
+                CheckStatus::Ok => "✔",
+                CheckStatus::Warning => "⚠️ ",
+                CheckStatus::Error => "✘",
+            };
+            let version = match (&item.version, &item.required) {
+                (Some(version), Some(required)) => format!("{} ({})", version, required),
+                (Some(version), None) => version.clone(),
+                (None, Some(required)) => format!("({})", required),
+                (None, None) => String::new(),
+            };
+            writeln!(out, "  {} {:<8} {:<24} {:<16} {}", mark, item.category, item.name, version, item.detail)?;
+            if let Some(fix) = &item.fix {
+                writeln!(out, "      fix: {}", fix)?;
+            }
+        }
+        writeln!(out)?;
+        writeln!(out, "{} error(s), {} warning(s)", r.count(CheckStatus::Error), r.count(CheckStatus::Warning))?;
+        Ok(())
+    })?;
+
+    let errors = report.count(CheckStatus::Error);
+    if errors > 0 {
+        anyhow::bail!("forge check found {} problem(s)", errors);
+    }
+    Ok(())
+}
+
+/// `--plugin`, then the project manifest, then detection; `None` outside a project.
+fn resolve_plugin(ctx: &ForgeContext, plugin_name: Option<String>, cwd: &Path) -> Result<Option<PluginMetadata>> {
+    if let Some(name) = plugin_name {
+        return load_plugin_metadata(ctx, &name)
+            .with_context(|| format!("Plugin {} is not installed", name))
+            .map(Some);
+    }
+    if let Some(manifest) = ForgeManifest::load(cwd)? {
+        return Ok(load_plugin_metadata(ctx, &manifest.plugin).ok());
+    }
+    Ok(detect_plugin(ctx, cwd))
+}
+
+/// Template files in the plugin's local template directory.
+fn template_files(plugin: &PluginMetadata) -> Option<Vec<PathBuf>> {
+    let root = Path::new(&plugin.templates);
+    if plugin.templates.is_empty() || !root.is_dir() {
+        return None;
+    }
+    let files = WalkDir::new(root).into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() != TEMPLATE_MANIFEST)
+        .map(|e| e.into_path())
+        .collect();
+    Some(files)
+}
+
+fn check_engines(ctx: &ForgeContext, plugin: Option<&PluginMetadata>, cwd: &Path, items: &mut Vec<CheckItem>) {
+    let registry = EngineRegistry::for_plugin(ctx, plugin);
+    let files = plugin.and_then(template_files);
+    let template = plugin
+        .filter(|_| files.is_some())
+        .map(|p| TemplateManifest::load(Path::new(&p.templates)));
+    let template = match template {
+        Some(Err(err)) => {
+            items.push(item("file", TEMPLATE_MANIFEST, CheckStatus::Error, format!("{:#}", err)));
+            None
+        }
+        Some(Ok(template)) => Some(template),
+        None => None,
+    };
+    let declared = declared_runtimes(plugin, template.as_ref());
+    let agent = match RenderAgent::configured(ctx, cwd) {
+        Ok(agent) => agent,
+        Err(err) => {
+            items.push(item("agent", "render_agent", CheckStatus::Error, format!("{:#}", err)));
+            None
+        }
+    };
+    let Some(files) = files else {
+        // Without local templates, anything an engine might need
+        for requirement in required_for::<PathBuf>(&registry, None, &declared) {
+            items.push(assess(&requirement, &declared));
+        }
+        return;
+    };
+
+    let plan = RenderPlan::new(agent.as_ref(), &registry, &files, declared);
+    for requirement in required_for(&registry, Some(&plan.local), &plan.local_runtimes) {
+        items.push(assess(&requirement, &plan.local_runtimes));
+    }
+    if let Some(agent) = agent {
+        let detail = format!("renders {} template(s)", plan.remote.len());
+        items.push(match agent.check(plan.remote_extensions(), &plan.remote_runtimes) {
+            Ok(()) => item("agent", &agent.url, CheckStatus::Ok, detail),
+            Err(err) => CheckItem {
+                fix: Some("start the agent with `forge agent serve`, install what it lacks, or unset render_agent".to_string()),
+                ..item("agent", &agent.url, CheckStatus::Error, format!("{:#}", err))
+            },
+        });
+    }
+}
+
+fn check_git(items: &mut Vec<CheckItem>) {
+    // Already reported when the plugin pins a git version
+    if items.iter().any(|i| i.category == "runtime" && i.name == "git") {
+        return;
+    }
+    items.push(match runtime_version("git") {
+        Some((line, version)) => CheckItem {
+            version: version.map(|v| v.to_string()),
+            ..item("tool", "git", CheckStatus::Ok, line)
+        },
+        None => CheckItem {
+            fix: Some("install git; it is needed to install plugins and clone templates".to_string()),
+            ..item("tool", "git", CheckStatus::Error, "not installed")
+        },
+    });
+}
+
+fn check_plugins(ctx: &ForgeContext, items: &mut Vec<CheckItem>) -> Result<()> {
+    for name in installed_plugins(ctx)? {
+        let check = match installed_plugin_problem(ctx, &name) {
+            Ok(None) => match load_plugin_metadata(ctx, &name) {
+                Ok(plugin) => CheckItem {
+                    version: Some(plugin.version),
+                    ..item("plugin", &name, CheckStatus::Ok, "checksum matches")
+                },
+                Err(err) => CheckItem {
+                    fix: Some(format!("forge plugin remove {0} && forge plugin install {0}", name)),
+                    ..item("plugin", &name, CheckStatus::Error, format!("plugin.json is unreadable: {}", err))
+                },
+            },
+            Ok(Some(problem)) => CheckItem {
+                fix: Some(format!("forge plugin remove {0} && forge plugin install {0}", name)),
+                ..item("plugin", &name, CheckStatus::Warning, problem)
+            },
+            Err(err) => item("plugin", &name, CheckStatus::Error, format!("{:#}", err)),
+        };
+        items.push(check);
+    }
+    Ok(())
+}
+
+/// State kept in forge's data directory: the index, the trust store and
+/// leftovers of interrupted installs.
+fn check_home(ctx: &ForgeContext, items: &mut Vec<CheckItem>) {
+    let paths = &ctx.paths;
+    let index_path = paths.plugin_index();
+    if index_path.exists() {
+        let parsed = fs::read_to_string(&index_path).ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok());
+        items.push(match parsed {
+            Some(_) => item("home", "plugin-index.json", CheckStatus::Ok, index_path.display().to_string()),
+            None => CheckItem {
+                fix: Some(format!("remove {} and reinstall your plugins", index_path.display())),
+                ..item("home", "plugin-index.json", CheckStatus::Error, "is not valid JSON")
+            },
+        });
+    }
+
+    if let Err(err) = TrustStore::load(ctx) {
+        items.push(CheckItem {
+            fix: Some(format!("fix or remove {} (approvals will be asked for again)", paths.trust_store().display())),
+            ..item("home", "trust.json", CheckStatus::Error, format!("{:#}", err))
+        });
+    }
+
+    let plugins_dir = paths.plugins_dir();
+    let staging: Vec<PathBuf> = fs::read_dir(&plugins_dir).into_iter().flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().starts_with(".staging-"))
+        .map(|e| e.path())
+        .collect();
+    for dir in staging {
+        items.push(CheckItem {
+            fix: Some(format!("rm -rf {}", dir.display())),
+            ..item("home", &dir.file_name().unwrap_or_default().to_string_lossy(), CheckStatus::Warning,
+                "left behind by an interrupted plugin install")
+        });
+    }
+}
+
+/// The downloaded plugin lists and the project's recorded LLM answers.
+fn check_cache(ctx: &ForgeContext, cwd: &Path, items: &mut Vec<CheckItem>) {
+    let registry_dir = ctx.cache_dir().join("registry");
+    items.push(match dir_problem(&registry_dir) {
+        Some(problem) => CheckItem {
+            fix: Some(format!("remove {} or set cache.dir to a writable directory", registry_dir.display())),
+            ..item("cache", "registry", CheckStatus::Error, format!("{} {}", registry_dir.display(), problem))
+        },
+        None if !registry_dir.exists() => item("cache", "registry", CheckStatus::Ok, format!("{} (not created yet)", registry_dir.display())),
+        None => item("cache", "registry", CheckStatus::Ok, registry_dir.display().to_string()),
+    });
+
+    for url in ctx.config.registry.urls.iter().filter(|url| is_remote(url)) {
+        let age = fs::metadata(cached_list(ctx, url)).and_then(|m| m.modified()).ok()
+            .map(|modified| modified.elapsed().unwrap_or_default());
+        items.push(match age {
+            None => item("cache", url, CheckStatus::Ok, "not cached yet; reading it needs the network"),
+            Some(age) if age > STALE_LIST_AGE => CheckItem {
+                fix: Some("run `forge plugin list` while online to refresh it".to_string()),
+                ..item("cache", url, CheckStatus::Warning, format!("cached copy is {} old", describe_age(age)))
+            },
+            Some(age) => item("cache", url, CheckStatus::Ok, format!("cached {} ago", describe_age(age))),
+        });
+    }
+
+    let llm_cache = cwd.join(LLM_CACHE_DIR);
+    if llm_cache.exists() {
+        items.push(match dir_problem(&llm_cache) {
+            Some(problem) => CheckItem {
+                fix: Some(format!("fix the permissions of {} or remove it and record again with --llm=record", llm_cache.display())),
+                ..item("cache", LLM_CACHE_DIR, CheckStatus::Error, problem)
+            },
+            None => {
+                let answers = fs::read_dir(&llm_cache).into_iter().flatten()
+                    .filter(|e| e.as_ref().is_ok_and(|e| e.path().is_file()))
+                    .count();
+                item("cache", LLM_CACHE_DIR, CheckStatus::Ok, format!("{} recorded answer(s)", answers))
+            }
+        });
+    }
+}
+
+/// Why forge cannot keep files in `dir`, if it cannot; a missing `dir` is
+/// created when first needed.
+fn dir_problem(dir: &Path) -> Option<String> {
+    if !dir.exists() {
+        return None;
+    }
+    if !dir.is_dir() {
+        return Some("is not a directory".to_string());
+    }
+    tempfile::NamedTempFile::new_in(dir).err().map(|err| format!("is not writable: {}", err))
+}
+
+/// `3 days`, `5 hours` or `12 minutes`.
+fn describe_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    match (minutes / (24 * 60), minutes / 60) {
+        (0, 0) => format!("{} minute(s)", minutes),
+        (0, hours) => format!("{} hour(s)", hours),
+        (days, _) => format!("{} day(s)", days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+    use crate::test_support::tree;
+
+    fn cache_items(ctx: &ForgeContext, cwd: &Path) -> Vec<(String, CheckStatus, String)> {
+        let mut items = Vec::new();
+        check_cache(ctx, cwd, &mut items);
+        items.into_iter().map(|i| (i.name, i.status, i.detail)).collect()
+    }
+
+    #[test]
+    fn reports_stale_lists_and_recorded_answers() {
+        let home = TempDir::new().unwrap();
+        let mut ctx = ForgeContext::for_tests(home.path());
+        let (fresh, stale, missing) = ("https://a.example/list.json", "https://b.example/list.json", "https://c.example/list.json");
+        ctx.config.registry.urls = vec!["plugin-list.json".to_string(), fresh.to_string(), stale.to_string(), missing.to_string()];
+        for url in [fresh, stale] {
+            fs::create_dir_all(cached_list(&ctx, url).parent().unwrap()).unwrap();
+            fs::write(cached_list(&ctx, url), "{}").unwrap();
+        }
+        let ten_days_ago = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        fs::File::options().write(true).open(cached_list(&ctx, stale)).unwrap().set_modified(ten_days_ago).unwrap();
+        let project = tree(&[(".forge/llm-cache/a.json", "{}"), (".forge/llm-cache/b.json", "{}")]);
+
+        let items = cache_items(&ctx, project.path());
+        let registry_dir = ctx.cache_dir().join("registry").display().to_string();
+        assert_eq!(items, [
+            ("registry".to_string(), CheckStatus::Ok, registry_dir),
+            (fresh.to_string(), CheckStatus::Ok, "cached 0 minute(s) ago".to_string()),
+            (stale.to_string(), CheckStatus::Warning, "cached copy is 10 day(s) old".to_string()),
+            (missing.to_string(), CheckStatus::Ok, "not cached yet; reading it needs the network".to_string()),
+            (LLM_CACHE_DIR.to_string(), CheckStatus::Ok, "2 recorded answer(s)".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn unusable_cache_directory_is_an_error() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        fs::create_dir_all(ctx.cache_dir()).unwrap();
+        fs::write(ctx.cache_dir().join("registry"), "").unwrap();
+
+        let items = cache_items(&ctx, home.path());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].1, CheckStatus::Error);
+        assert!(items[0].2.ends_with("is not a directory"), "{}", items[0].2);
+    }
+}
@@ -0,0 +1,108 @@
+// src/commands/config.rs
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use serde::Serialize;
+use crate::config::{config_key, insert, parse_value, read_table, ConfigKey, ConfigLayers, ForgeConfig, CONFIG_KEYS, PROJECT_CONFIG};
+use crate::context::ForgeContext;
+use crate::secrets::SecretRef;
+
+/// A setting as shown by `forge config get` and `forge config list`.
+#[derive(Debug, Serialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    /// `null` when no layer sets it.
+    pub value: Option<serde_json::Value>,
+    /// `default`, the file that sets it, or `env <VARIABLE>`.
+    pub source: Option<String>,
+    /// Environment variable that overrides the files.
+    pub env: Option<&'static str>,
+    pub description: &'static str,
+    #[serde(skip)]
+    display: Option<String>,
+}
+
+/// Result of `forge config set`.
+#[derive(Debug, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub path: PathBuf,
+}
+
+fn config_entry(layers: &ConfigLayers, key: &ConfigKey) -> Result<ConfigEntry> {
+    let found = layers.get(key.key);
+    Ok(ConfigEntry {
+        key: key.key.to_string(),
+        value: found.map(|(value, _)| serde_json::to_value(value)).transpose()?,
+        source: found.map(|(_, source)| source.describe(Some(key))),
+        env: key.env,
+        description: key.help,
+        display: found.map(|(value, _)| match value {
+            toml::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }),
+    })
+}
+
+/// `forge config list`: every setting, its effective value and where it comes from.
+pub fn config_list(ctx: &ForgeContext, layers: &ConfigLayers) -> Result<()> {
+    let entries = CONFIG_KEYS.iter()
+        .map(|key| config_entry(layers, key))
+        .collect::<Result<Vec<_>>>()?;
+
+    ctx.output.emit(&entries, |entries, out| {
+        for entry in entries {
+            let value = entry.display.as_deref().unwrap_or("(unset)");
+            let source = entry.source.as_deref().map(|s| format!("({})", s)).unwrap_or_default();
+            writeln!(out, "{:<22} {:<40} {}", entry.key, value, source)?;
+        }
+        Ok(())
+    })
+}
+
+/// `forge config get <key>`: the effective value, printed bare for scripts.
+pub fn config_get(ctx: &ForgeContext, key: &str, layers: &ConfigLayers) -> Result<()> {
+    let key = config_key(key)?;
+    let entry = config_entry(layers, key)?;
+
+    ctx.output.emit(&entry, |entry, out| {
+        if let Some(value) = &entry.display {
+            writeln!(out, "{}", value)?;
+        }
+        Ok(())
+    })
+}
+
+/// `forge config set <key> <value>`: write the setting to the project's
+/// `.forge/config.toml`, or with `global` to the global `config.toml`.
+pub fn config_set(ctx: &ForgeContext, key: &str, value: &str, global: bool) -> Result<()> {
+    let key = config_key(key)?;
+    if key.key == "llm.api_key" {
+        SecretRef::parse(value)
+            .context("llm.api_key takes a secret reference; forge never stores the key itself")?;
+    }
+    if key.global_only && !global {
+        anyhow::bail!("{} can only be set globally, so a project cannot change it for you; use `forge config set --global {} <value>`", key.key, key.key);
+    }
+    let path = match global {
+        true => ctx.paths.global_config(),
+        false => std::env::current_dir()?.join(PROJECT_CONFIG),
+    };
+
+    let parsed = parse_value(key, value)?;
+    let mut table = read_table(&path)?.unwrap_or_default();
+    insert(&mut table, key.key, parsed.clone());
+    toml::Value::Table(table.clone()).try_into::<ForgeConfig>()
+        .with_context(|| format!("Invalid value '{}' for {}", value, key.key))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, toml::to_string(&table)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let change = ConfigChange { key: key.key.to_string(), value: serde_json::to_value(&parsed)?, path };
+    ctx.output.emit(&change, |c, out| writeln!(out, "Set {} in {}.", c.key, c.path.display()))
+}
@@ -0,0 +1,67 @@
+// src/commands/detect.rs
+use std::fmt::Write;
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use crate::context::ForgeContext;
+use crate::detect::{find_components, rank_plugins, DEFAULT_SCAN_DEPTH};
+use crate::plugin::plugin_commands::detection_candidates;
+
+/// Explain which plugins match the project at `path` and why.
+pub fn run_detect(ctx: &ForgeContext, path: Option<PathBuf>, recursive: bool) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => std::env::current_dir().context("Failed to get current directory")?,
+    };
+    if !path.is_dir() {
+        anyhow::bail!("{} is not a directory", path.display());
+    }
+
+    let plugins = detection_candidates(ctx);
+
+    if recursive {
+        let components = find_components(&path, &plugins, DEFAULT_SCAN_DEPTH);
+        ctx.output.emit(&components, |components, out| {
+            writeln!(out, "Components of {}", path.display())?;
+            for component in components {
+                writeln!(out, "  {:<20} {:<30} {}", component.name, component.path, component.plugin)?;
+            }
+            Ok(())
+        })?;
+        if components.is_empty() {
+            anyhow::bail!("Unable to detect any plugin below {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let detection = rank_plugins(&path, &plugins);
+
+    ctx.output.emit(&detection, |d, out| {
+        writeln!(out, "Detecting plugins for {}", d.path.display())?;
+        if d.candidates.is_empty() {
+            writeln!(out, "No plugins installed and no plugin-list.json found.")?;
+        }
+        for candidate in &d.candidates {
+            let marker = if candidate.matched { "✅" } else { "❌" };
+            writeln!(out)?;
+            writeln!(out, "{} {} {} (score {})", marker, candidate.plugin, candidate.version, candidate.score)?;
+            for check in &candidate.checks {
+                let mark = if check.passed { "✔" } else { "✘" };
+                writeln!(out, "    {} {:<40} {}", mark, check.rule, check.detail)?;
+            }
+        }
+        writeln!(out)?;
+        match &d.selected {
+            Some(selected) => writeln!(out, "Selected plugin: {}", selected)?,
+            None => writeln!(out, "✘ No plugin matched.")?,
+        }
+        if !d.ties.is_empty() {
+            writeln!(out, "⚠️  Tied with: {} (pass --plugin to choose)", d.ties.join(", "))?;
+        }
+        Ok(())
+    })?;
+
+    if detection.selected.is_none() {
+        anyhow::bail!("Unable to detect plugin for {}", path.display());
+    }
+    Ok(())
+}
@@ -43,11 +43,17 @@
 
 // src/generate.rs
 use clap::Subcommand;
-use std::path::PathBuf;
-use anyhow::Result;
-use crate::plugin::plugin_commands::{detect_plugin};
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use crate::context::ForgeContext;
+use crate::lockfile::{apply_lock, template_key, LockedTemplate};
+use crate::manifest::{Component, ForgeManifest};
+use crate::plugin::plugin_commands::{detect_plugin, load_plugin_metadata, locked_plugin, verify_installed_plugin};
 use crate::plugin::PluginMetadata;
-use crate::template_processor::process_templates;
+use crate::template_processor::{process_templates, ConflictPolicy, GenerateSummary};
+use crate::templates::template_commit;
+use crate::verify::digest_dir;
 // use std::fs;
 // extern crate fs_extra;
 // use anyhow::{Result, Context};
@@ -65,17 +71,122 @@ pub enum GenerateSubcommand {
         /// Optional path to templates (overrides plugin default)
         #[clap(short, long)]
         templates: Option<PathBuf>,
+
+        /// Only generate this component from .forge/manifest.json
+        #[clap(short, long, conflicts_with = "all")]
+        component: Option<String>,
+
+        /// Generate every component from .forge/manifest.json, each into <out>/<component path>
+        #[clap(short, long, conflicts_with = "templates")]
+        all: bool,
+
+        /// What to do with files that already exist (default: generate.on_conflict, or overwrite)
+        #[clap(long, value_enum)]
+        on_conflict: Option<ConflictPolicy>,
     }
 }
 
-pub fn handle_generate(cmd: GenerateSubcommand) -> Result<()> {
+pub fn handle_generate(ctx: &ForgeContext, cmd: GenerateSubcommand) -> Result<()> {
+    let output = ctx.output;
     match cmd {
-        GenerateSubcommand::Generate { out, templates } => {
-            let cwd = std::env::current_dir().unwrap();
+        GenerateSubcommand::Generate { out, templates, component, all, on_conflict } => {
+            let cwd = std::env::current_dir().context("Failed to get current directory")?;
+            let on_conflict = on_conflict.unwrap_or(ctx.config.generate.on_conflict);
+
+            if component.is_none() && !all {
+                let plugin: PluginMetadata = detect_plugin(ctx, &cwd)
+                    .context("Unable to detect plugin. Run `forge detect` to see why.")?;
+                let summary = generate_with_plugin(ctx, &cwd, &plugin, templates, out, on_conflict)?;
+                return output.emit(&summary, print_summary);
+            }
+
+            let manifest = ForgeManifest::load(&cwd)?
+                .context("No .forge/manifest.json found. Run `forge init --recursive` first.")?;
+            if manifest.components.is_empty() {
+                anyhow::bail!("The manifest declares no components. Run `forge init --recursive --force` to detect them.");
+            }
+            let targets: Vec<&Component> = match &component {
+                Some(name) => vec![manifest.components.iter()
+                    .find(|c| &c.name == name || &c.path == name)
+                    .with_context(|| format!("No component named {} in the manifest", name))?],
+                None => manifest.components.iter().collect(),
+            };
 
-            let plugin: PluginMetadata = detect_plugin(&cwd).unwrap();
-            process_templates(&plugin, templates, out)?;
+            let mut summaries = Vec::new();
+            for target in targets {
+                let plugin = load_plugin_metadata(ctx, &target.plugin)
+                    .with_context(|| format!("Failed to load plugin {} for component {}", target.plugin, target.name))?;
+                let component_out = if target.path == "." { out.clone() } else { out.join(&target.path) };
+                summaries.push(generate_with_plugin(ctx, &cwd, &plugin, templates.clone(), component_out, on_conflict)?);
+            }
+
+            if all {
+                output.emit(&summaries, |summaries, out| summaries.iter().try_for_each(|s| print_summary(s, out)))?;
+            } else {
+                output.emit(&summaries[0], print_summary)?;
+            }
         }
     }
     Ok(())
 }
+
+fn print_summary(summary: &GenerateSummary, out: &mut String) -> fmt::Result {
+    for file in &summary.files {
+        match &file.generated_by {
+            Some(model) => match &file.prompt {
+                Some(prompt) => writeln!(out, "  generated {} ({}, prompt {})", file.output.display(), model, prompt)?,
+                None => writeln!(out, "  generated {} ({})", file.output.display(), model)?,
+            },
+            None => writeln!(out, "  rendered {}", file.output.display())?,
+        }
+    }
+    for path in &summary.skipped {
+        writeln!(out, "  kept {} (already exists)", path.display())?;
+    }
+    writeln!(out, "✅ Generated {} file(s) in {} using plugin {}.",
+        summary.files.len(), summary.output_dir.display(), summary.plugin)?;
+    Ok(())
+}
+
+fn generate_with_plugin(
+    ctx: &ForgeContext,
+    project_dir: &Path,
+    plugin: &PluginMetadata,
+    templates: Option<PathBuf>,
+    out: PathBuf,
+    on_conflict: ConflictPolicy,
+) -> Result<GenerateSummary> {
+    verify_installed_plugin(ctx, &plugin.name)?;
+
+    // Pin the plugin and template tree before rendering so --locked fails early
+    let template_root = templates.clone().unwrap_or_else(|| PathBuf::from(&plugin.templates));
+    let locked_template = LockedTemplate {
+        name: template_key(&plugin.name, &template_root.file_name().unwrap_or_default().to_string_lossy()),
+        source: template_source(ctx, project_dir, plugin, &template_root)?,
+        commit: template_commit(&template_root),
+        sha256: digest_dir(&template_root)?,
+    };
+    apply_lock(project_dir, vec![locked_plugin(ctx, plugin)?], vec![locked_template], ctx.locked)?;
+
+    process_templates(ctx, plugin, templates, out, on_conflict)
+}
+
+/// Where the templates come from, written the same on every machine:
+/// `plugin:<name>/<path>` for templates bundled with the installed plugin,
+/// else the path relative to the project when they are inside it.
+fn template_source(ctx: &ForgeContext, project_dir: &Path, plugin: &PluginMetadata, template_root: &Path) -> Result<String> {
+    let slashed = |path: &Path| path.to_string_lossy().replace('\\', "/");
+    let plugin_dir = ctx.paths.plugins_dir().join(&plugin.name);
+    if let Ok(relative) = template_root.strip_prefix(&plugin_dir) {
+        return Ok(match relative.as_os_str().is_empty() {
+            true => format!("plugin:{}", plugin.name),
+            false => format!("plugin:{}/{}", plugin.name, slashed(relative)),
+        });
+    }
+    let absolute = std::path::absolute(template_root)?;
+    Ok(match absolute.strip_prefix(project_dir) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => slashed(relative),
+        Err(_) => slashed(template_root),
+    })
+}
@@ -1,71 +1,131 @@
 // src/commands/init.rs
+use std::fmt::Write;
 use std::{fs, path::PathBuf};
 use chrono::Utc;
-use serde::{Deserialize, Serialize};
 use anyhow::{Result, Context};
-use crate::plugin::plugin_commands::{detect_plugin, load_plugin_metadata};
+use crate::context::ForgeContext;
+use crate::detect::{find_components, DEFAULT_SCAN_DEPTH};
+use crate::manifest::{manifest_path, Component, ForgeManifest, TemplateEntry};
+use crate::lockfile::{apply_lock, lock_path, template_key, ForgeLock, LockedTemplate};
+use crate::plugin::plugin_commands::{detect_plugin, detection_candidates, load_plugin_metadata, locked_plugin, verify_installed_plugin};
 use crate::plugin::PluginMetadata;
-use crate::templates::clone_template;
+use crate::templates::{checkout_template_commit, clone_template, template_commit};
+use crate::verify::{require_verified, verify_dir};
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TemplateEntry {
-    name: String,
-    url: String,
+pub struct InitOptions {
+    pub plugin: Option<String>,
+    pub template: Option<String>,
+    pub force: bool,
+    /// Also detect components in subdirectories (monorepos).
+    pub recursive: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ForgeManifest {
-    plugin: String,
-    templates: Vec<TemplateEntry>,
-    created: String,
-}
-
-pub fn run_forge_init(plugin_arg: Option<String>, template_arg: Option<String>, force: bool) -> Result<()> {
+pub fn run_forge_init(ctx: &ForgeContext, options: InitOptions) -> Result<()> {
+    let InitOptions { plugin: plugin_arg, template: template_arg, force, recursive } = options;
     let cwd = std::env::current_dir().context("Failed to get current directory")?;
     let forge_dir = cwd.join(".forge");
-    let manifest_path = forge_dir.join("manifest.json");
+    let manifest_path = manifest_path(&cwd);
 
     if manifest_path.exists() && !force {
         eprintln!("🛑 Manifest already exists. Use --force to overwrite.");
         std::process::exit(1);
     }
 
+    let mut components = if recursive {
+        find_components(&cwd, &detection_candidates(ctx), DEFAULT_SCAN_DEPTH)
+    } else {
+        Vec::new()
+    };
+
     // Detect plugin
-    let plugin: PluginMetadata = match plugin_arg {
-        Some(plugin_name) => load_plugin_metadata(&plugin_name).context("Failed to load plugin metadata")?,
-        None => detect_plugin(&cwd).unwrap_or_else(|| {
-            println!("✘ Unable to detect plugin. Use --plugin to specify.");
-            std::process::exit(1);
-        }),
+    let (plugin, root_matched): (PluginMetadata, bool) = match plugin_arg {
+        Some(plugin_name) => (load_plugin_metadata(ctx, &plugin_name).context("Failed to load plugin metadata")?, true),
+        None => match detect_plugin(ctx, &cwd) {
+            Some(plugin) => (plugin, true),
+            // A monorepo root may match nothing itself; fall back to its first component
+            None if !components.is_empty() => (load_plugin_metadata(ctx, &components[0].plugin)
+                .context("Failed to load plugin metadata")?, false),
+            None => {
+                println!("✘ Unable to detect plugin. Use --plugin to specify, or run `forge detect` to see why.");
+                std::process::exit(1);
+            }
+        },
     };
+    if recursive {
+        if root_matched {
+            components.retain(|c| c.path != ".");
+            components.insert(0, Component { name: "root".to_string(), path: ".".to_string(), plugin: plugin.name.clone() });
+        }
+        for component in &components {
+            verify_installed_plugin(ctx, &component.plugin)?;
+        }
+    }
     // This might be redundant since detect plugin already loads metadata
-    let plugin_meta = load_plugin_metadata(&plugin.name).context("Failed to load plugin metadata")?;
+    let plugin_meta = load_plugin_metadata(ctx, &plugin.name).context("Failed to load plugin metadata")?;
+    verify_installed_plugin(ctx, &plugin.name)?;
 
     // Determine template URL
-    let (template_name, template_url) = match template_arg {
+    let (template_name, template_url, expected_sha256) = match template_arg {
         Some(url) => {
             let name = infer_template_name(&url);
-            (name, url)
+            (name, url, None)
+        },
+        None => {
+            if plugin_meta.templates.is_empty() {
+                anyhow::bail!("Plugin {} has no templates defined", plugin_meta.name);
+            }
+            (infer_template_name(&plugin_meta.templates), plugin_meta.templates.clone(), plugin_meta.templates_sha256.clone())
         },
-        None => plugin_meta.templates.iter().next()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .expect("Plugin has no templates defined"),
     };
 
     let target_dir = forge_dir.join("templates").join(&template_name);
     clone_template(&template_url, &target_dir)?;
 
-    // Write manifest
+    // Reuse the pinned commit so every checkout of the project renders the same templates
+    let lock_key = template_key(&plugin_meta.name, &template_name);
+    let lock = ForgeLock::load(&lock_path(&cwd))?;
+    if let Some(commit) = lock.as_ref()
+        .and_then(|l| l.template(&lock_key))
+        .filter(|t| t.source == template_url)
+        .and_then(|t| t.commit.as_deref()) {
+        checkout_template_commit(&target_dir, commit)?;
+    }
+
+    let verification = verify_dir(&target_dir, expected_sha256.as_deref(), None, &ctx.paths.trusted_keys_dir())?;
+    if let Err(err) = require_verified(&format!("template {}", template_url), &verification, ctx.allow_unverified) {
+        fs::remove_dir_all(&target_dir).ok();
+        return Err(err);
+    }
+
+    let locked_template = LockedTemplate {
+        name: lock_key,
+        source: template_url.clone(),
+        commit: template_commit(&target_dir),
+        sha256: verification.sha256.clone(),
+    };
+    apply_lock(&cwd, vec![locked_plugin(ctx, &plugin_meta)?], vec![locked_template], ctx.locked)?;
+
+    // Write manifest, keeping settings from a previous one
+    let previous = ForgeManifest::load(&cwd).ok().flatten();
+    let (render_agent, llm, secrets) = previous.map(|m| (m.render_agent, m.llm, m.secrets)).unwrap_or_default();
     let manifest = ForgeManifest {
         plugin: plugin.name,
         templates: vec![TemplateEntry { name: template_name, url: template_url.to_string() }],
         created: Utc::now().to_rfc3339(),
+        components,
+        render_agent,
+        llm,
+        secrets,
     };
-    fs::create_dir_all(&forge_dir)?;
-    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    manifest.save(&cwd)?;
 
-    println!("✅ Initialized .forge/manifest.json with plugin and template.");
-    Ok(())
+    ctx.output.emit(&manifest, |m, out| {
+        for component in &m.components {
+            writeln!(out, "  component {} ({}) -> {}", component.name, component.path, component.plugin)?;
+        }
+        writeln!(out, "✅ Initialized .forge/manifest.json with plugin and template.")?;
+        Ok(())
+    })
 }
 
 fn infer_template_name(url: &str) -> String {
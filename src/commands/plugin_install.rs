@@ -1,43 +1,220 @@
 // src/commands/plugin_install.rs
+use std::fmt::Write;
 use std::fs;
-use std::env;
 use std::path::PathBuf;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use anyhow::{Result, Context};
-use crate::plugin::{PluginMetadata};
-
-pub fn plugin_install(plugin_name: &str, index_path: PathBuf) -> Result<()> {
-    println!("Installing plugin: {}", plugin_name);
-
-    // Simulate plugin installation by creating dummy plugin.json
-    let plugin_dir = dirs::home_dir()
-        .context("Could not determine home directory")?
-        .join(".forge/plugins")
-        .join(plugin_name);
-    fs::create_dir_all(&plugin_dir)?;
-
-    let plugin_json_path = plugin_dir.join("plugin.json");
-
-    let plugin_metadata = PluginMetadata {
-        name: plugin_name.to_string(),
-        version: "0.1.0".to_string(),
-        description: Some("Sample installed plugin".to_string()),
-        templates: String::new(),
-        detect: None,
-        actions: None,
-        custom_renderer_command: None
-    };
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use crate::context::ForgeContext;
+use crate::plugin::{check_plugin_name, PluginMetadata};
+use crate::registry::{read_registry, registry_locations};
+use crate::renderer::{EngineInfo, EngineRegistry, EngineSource};
+use crate::templates::clone_template;
+use crate::verify::{require_verified, verify_dir, Verification};
+
+/// Result of `forge plugin install` / `forge plugin remove`.
+#[derive(Debug, Serialize)]
+pub struct PluginChange {
+    pub plugin: String,
+    pub action: &'static str,
+    pub path: Option<String>,
+    pub verification: Option<Verification>,
+    /// Dependencies installed, or dependents removed, along with this plugin.
+    pub cascade: Vec<PluginChange>,
+}
+
+/// One row of `forge plugin list`.
+#[derive(Debug, Serialize)]
+pub struct PluginListEntry {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub repo: Option<String>,
+    pub installed: bool,
+    pub installed_version: Option<String>,
+    pub installed_path: Option<String>,
+    pub verified: bool,
+}
+
+/// Result of `forge plugin info`.
+#[derive(Debug, Serialize)]
+pub struct PluginInfo {
+    #[serde(flatten)]
+    pub entry: PluginListEntry,
+    pub metadata: Option<PluginMetadata>,
+    /// Template engines the plugin adds.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub engines: Vec<EngineInfo>,
+}
+
+/// Look up a per-version value (`checksums`, `signatures`) from a registry entry.
+fn registry_version_value<'a>(entry: &'a serde_json::Value, key: &str, version: &str) -> Option<&'a str> {
+    entry.get(key)?.get(version)?.as_str()
+}
+
+fn read_index(index_path: &PathBuf) -> Result<HashMap<String, serde_json::Value>> {
+    if !index_path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(index_path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn write_index(index_path: &PathBuf, index: &HashMap<String, serde_json::Value>) -> Result<()> {
+    let json = serde_json::to_string_pretty(index)?;
+    fs::create_dir_all(index_path.parent().unwrap())?;
+    fs::write(index_path, json)?;
+    Ok(())
+}
+
+fn is_installed(entry: &serde_json::Value) -> bool {
+    entry.get("installed").and_then(|v| v.as_bool()) == Some(true)
+}
+
+/// Plugins that an index entry recorded as dependencies at install time.
+fn recorded_dependencies(entry: &serde_json::Value) -> Vec<String> {
+    entry.get("depends_on")
+        .and_then(|v| v.as_object())
+        .map(|deps| deps.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Does `version` satisfy the semver range `requirement`?
+fn version_satisfies(version: &str, requirement: &str) -> Result<bool> {
+    let req = VersionReq::parse(requirement)
+        .with_context(|| format!("Invalid version range '{}'", requirement))?;
+    Ok(Version::parse(version).is_ok_and(|v| req.matches(&v)))
+}
+
+pub fn plugin_install(ctx: &ForgeContext, plugin_name: &str, index_path: PathBuf) -> Result<()> {
+    let registry = read_registry(ctx)?;
+    let mut chain = Vec::new();
+    let change = install_plugin(ctx, plugin_name, &registry, &index_path, &mut chain)?;
+
+    ctx.output.emit(&change, |c, out| {
+        for dep in &c.cascade {
+            writeln!(out, "Dependency installed: {}", dep.plugin)?;
+        }
+        writeln!(out, "Plugin installed: {}", c.plugin)?;
+        Ok(())
+    })
+}
+
+/// Install a plugin after installing any `depends_on` plugins that are missing
+/// or outside the required version range. `chain` holds the plugins currently
+/// being installed, to report dependency cycles.
+fn install_plugin(
+    ctx: &ForgeContext,
+    plugin_name: &str,
+    registry: &HashMap<String, serde_json::Value>,
+    index_path: &PathBuf,
+    chain: &mut Vec<String>,
+) -> Result<PluginChange> {
+    check_plugin_name(plugin_name)?;
+    if chain.iter().any(|p| p == plugin_name) {
+        anyhow::bail!("Plugin dependency cycle: {} -> {}", chain.join(" -> "), plugin_name);
+    }
+    ctx.output.note(format!("Installing plugin: {}", plugin_name));
+
+    // Without a plugin list entry, a sample plugin.json with version 0.1.0 is installed
+    let registry_entry = registry.get(plugin_name);
+    let version = match registry_entry {
+        Some(entry) => entry.get("version").and_then(|v| v.as_str())
+            .with_context(|| format!("Plugin {} has no version in the plugin list", plugin_name))?,
+        None => "0.1.0",
+    }.to_string();
+
+    // Replacing an installed plugin must not break the others that depend on it;
+    // the plugins in `chain` are being reinstalled with new ranges
+    let mut broken = Vec::new();
+    for (name, entry) in &read_index(index_path)? {
+        if !is_installed(entry) || chain.contains(name) {
+            continue;
+        }
+        let Some(requirement) = entry.get("depends_on").and_then(|d| d.get(plugin_name)).and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if !version_satisfies(&version, requirement)? {
+            broken.push(format!("{} (needs {})", name, requirement));
+        }
+    }
+    if !broken.is_empty() {
+        broken.sort();
+        anyhow::bail!("Installing {} {} would break installed plugin(s) that depend on it: {}",
+            plugin_name, version, broken.join(", "));
+    }
 
-    let json = serde_json::to_string_pretty(&plugin_metadata)?;
-    fs::write(plugin_json_path, json)?;
+    let plugins_dir = ctx.paths.plugins_dir();
+    let plugin_dir = plugins_dir.join(plugin_name);
+    let staging_dir = plugins_dir.join(format!(".staging-{}", plugin_name));
+    fs::create_dir_all(&plugins_dir)?;
+
+    let repo = registry_entry.and_then(|e| e.get("repo")).and_then(|v| v.as_str());
+    match repo {
+        Some(repo) => clone_template(repo, &staging_dir)?,
+        None => {
+            // No registry entry to fetch from: simulate installation with a sample plugin.json
+            if staging_dir.exists() {
+                fs::remove_dir_all(&staging_dir)?;
+            }
+            fs::create_dir_all(&staging_dir)?;
+            let plugin_metadata = PluginMetadata {
+                name: plugin_name.to_string(),
+                version: "0.1.0".to_string(),
+                description: Some("Sample installed plugin".to_string()),
+                templates: String::new(),
+                templates_sha256: None,
+                detect: None,
+                actions: None,
+                secrets: Vec::new(),
+                custom_renderer_command: None,
+                renderers: Vec::new(),
+                wasm_renderer: None,
+                runtimes: BTreeMap::new(),
+                prompts: BTreeMap::new(),
+                container: None,
+                depends_on: None,
+            };
+            let json = serde_json::to_string_pretty(&plugin_metadata)?;
+            fs::write(staging_dir.join("plugin.json"), json)?;
+        }
+    }
+
+    let verification = verify_dir(
+        &staging_dir,
+        registry_entry.and_then(|e| registry_version_value(e, "checksums", &version)),
+        registry_entry.and_then(|e| registry_version_value(e, "signatures", &version)),
+        &ctx.paths.trusted_keys_dir(),
+    )?;
+    if let Err(err) = require_verified(&format!("plugin {} {}", plugin_name, version), &verification, ctx.allow_unverified) {
+        fs::remove_dir_all(&staging_dir).ok();
+        return Err(err);
+    }
+
+    let depends_on: BTreeMap<String, String> = fs::read_to_string(staging_dir.join("plugin.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<PluginMetadata>(&content).ok())
+        .and_then(|metadata| metadata.depends_on)
+        .map(|deps| deps.into_iter().collect())
+        .unwrap_or_default();
+
+    // Resolve dependencies before this plugin becomes visible as installed
+    chain.push(plugin_name.to_string());
+    let mut cascade = Vec::new();
+    if let Err(err) = install_dependencies(ctx, plugin_name, &depends_on, registry, index_path, chain, &mut cascade) {
+        fs::remove_dir_all(&staging_dir).ok();
+        return Err(err);
+    }
+    chain.pop();
+
+    if plugin_dir.exists() {
+        fs::remove_dir_all(&plugin_dir)?;
+    }
+    fs::rename(&staging_dir, &plugin_dir)?;
 
     // Update plugin-index.json
-    let mut index = if index_path.exists() {
-        let content = fs::read_to_string(&index_path)?;
-        serde_json::from_str::<HashMap<String, serde_json::Value>>(&content)?
-    } else {
-        HashMap::new()
-    };
+    let mut index = read_index(index_path)?;
 
     let mut entry = serde_json::Map::new();
     entry.insert("installed".to_string(), serde_json::Value::Bool(true));
@@ -45,75 +222,249 @@ pub fn plugin_install(plugin_name: &str, index_path: PathBuf) -> Result<()> {
         "path".to_string(),
         serde_json::Value::String(plugin_dir.to_string_lossy().into()),
     );
+    entry.insert("version".to_string(), serde_json::Value::String(version));
+    entry.insert("sha256".to_string(), serde_json::Value::String(verification.sha256.clone()));
+    entry.insert("verified".to_string(), serde_json::Value::Bool(verification.is_verified()));
+    entry.insert("depends_on".to_string(), serde_json::to_value(&depends_on)?);
 
     index.insert(plugin_name.to_string(), serde_json::Value::Object(entry));
+    write_index(index_path, &index)?;
 
-    let json = serde_json::to_string_pretty(&index)?;
-    fs::create_dir_all(index_path.parent().unwrap())?;
-    fs::write(index_path, json)?;
+    Ok(PluginChange {
+        plugin: plugin_name.to_string(),
+        action: "installed",
+        path: Some(plugin_dir.to_string_lossy().into()),
+        verification: Some(verification),
+        cascade,
+    })
+}
 
-    println!("Plugin installed: {}", plugin_name);
-    Ok(())
+/// Install the missing or out-of-range `depends_on` of `plugin_name` into
+/// `cascade`. When one fails, the dependencies this call installed are
+/// removed again; those that replaced an installed version cannot be, and the
+/// error names them.
+fn install_dependencies(
+    ctx: &ForgeContext,
+    plugin_name: &str,
+    depends_on: &BTreeMap<String, String>,
+    registry: &HashMap<String, serde_json::Value>,
+    index_path: &PathBuf,
+    chain: &mut Vec<String>,
+    cascade: &mut Vec<PluginChange>,
+) -> Result<()> {
+    let before = read_index(index_path)?;
+    let mut install = || -> Result<()> {
+        for (dep_name, requirement) in depends_on {
+            let installed_version = read_index(index_path)?
+                .get(dep_name)
+                .filter(|e| is_installed(e))
+                .and_then(|e| e.get("version").and_then(|v| v.as_str()).map(String::from));
+            if let Some(installed_version) = &installed_version
+                && version_satisfies(installed_version, requirement)? {
+                continue;
+            }
+
+            let available = registry.get(dep_name)
+                .and_then(|e| e.get("version"))
+                .and_then(|v| v.as_str())
+                .with_context(|| format!("Plugin {} depends on {} {}, which is not in the plugin list", plugin_name, dep_name, requirement))?;
+            if !version_satisfies(available, requirement)? {
+                anyhow::bail!("Plugin {} depends on {} {}, but only {} is available", plugin_name, dep_name, requirement, available);
+            }
+
+            let change = install_plugin(ctx, dep_name, registry, index_path, chain)
+                .with_context(|| format!("Failed to install dependency {} of {}", dep_name, plugin_name))?;
+            cascade.push(change);
+        }
+        Ok(())
+    };
+    let Err(err) = install() else {
+        return Ok(());
+    };
+
+    let mut index = read_index(index_path)?;
+    let (mut removed, mut kept) = (Vec::new(), Vec::new());
+    let mut pending: Vec<&PluginChange> = cascade.iter().collect();
+    while let Some(change) = pending.pop() {
+        pending.extend(&change.cascade);
+        match before.get(&change.plugin) {
+            Some(previous) if is_installed(previous) => kept.push(change.plugin.clone()),
+            previous => {
+                if let Some(path) = &change.path {
+                    fs::remove_dir_all(path).ok();
+                }
+                match previous {
+                    Some(previous) => index.insert(change.plugin.clone(), previous.clone()),
+                    None => index.remove(&change.plugin),
+                };
+                removed.push(change.plugin.clone());
+            }
+        }
+    }
+    write_index(index_path, &index)?;
+    cascade.clear();
+
+    removed.sort();
+    kept.sort();
+    let mut undone = Vec::new();
+    if !removed.is_empty() {
+        undone.push(format!("removed the dependencies installed for it again: {}", removed.join(", ")));
+    }
+    if !kept.is_empty() {
+        undone.push(format!("these dependencies stay at their new version: {}", kept.join(", ")));
+    }
+    if undone.is_empty() {
+        return Err(err);
+    }
+    Err(err.context(format!("Installing {} failed; {}", plugin_name, undone.join("; "))))
 }
 
-pub fn plugin_list(installed_plugin_path: PathBuf) -> Result<()> {
-    let plugin_list_path = env::current_dir().unwrap_or_default().join("plugin-list.json");
-    let mut plugin_index: HashMap<String, serde_json::Value> = HashMap::new();
+/// Merge the available plugin list with the local install index, sorted by name.
+fn collect_plugins(ctx: &ForgeContext, installed_plugin_path: &PathBuf) -> Result<Vec<PluginListEntry>> {
     let mut installed_plugins: HashMap<String, serde_json::Value> = HashMap::new();
 
-    if ! fs::exists(&installed_plugin_path)? {
-        println!("Plugin path {} does not exist.", installed_plugin_path.display());
+    if ! fs::exists(installed_plugin_path)? {
+        ctx.output.note(format!("Plugin path {} does not exist.", installed_plugin_path.display()));
     } else {
       let installed_plugin_content = fs::read_to_string(installed_plugin_path)?;
       installed_plugins = serde_json::from_str(&installed_plugin_content).unwrap_or_else(|_| HashMap::new());
 
     }
 
-    if ! fs::exists(&plugin_list_path)?  {
-        println!("Plugin index {} not found.", plugin_list_path.display());
-    } else {
-      let plugin_list_content = fs::read_to_string(plugin_list_path)?;
-      plugin_index = serde_json::from_str(&plugin_list_content)
-          .context("Failed to parse plugin list content as JSON")?;
+    let plugin_index = read_registry(ctx)?;
+    if plugin_index.is_empty() {
+        ctx.output.note(format!("No plugins found in {}.", registry_locations(ctx)));
     }
 
-    for (name, details) in plugin_index.iter_mut() {
-        if let Some(installed_details) = installed_plugins.get(name) {
-            details["installed"] = serde_json::Value::Bool(true);
-            if let Some(install_path) = installed_details.get("install_path") {
-                details["installed_path"] = install_path.clone();
-            }
-            if let Some(version) = installed_details.get("version") {
-                details["installed_version"] = version.clone();
+    let text = |v: &serde_json::Value, key: &str| v.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    let mut plugins: Vec<PluginListEntry> = plugin_index.iter()
+        .map(|(name, details)| {
+            let installed_details = installed_plugins.get(name)
+                .filter(|d| d.get("installed").and_then(|v| v.as_bool()) != Some(false));
+            PluginListEntry {
+                name: name.clone(),
+                version: text(details, "version"),
+                description: text(details, "description"),
+                repo: text(details, "repo"),
+                installed: installed_details.is_some(),
+                installed_version: installed_details.and_then(|d| text(d, "version")),
+                installed_path: installed_details.and_then(|d| text(d, "path")),
+                verified: installed_details
+                    .and_then(|d| d.get("verified"))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false),
             }
-        } else {
-            details["installed"] = serde_json::Value::Bool(false);
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(plugins)
+}
+
+pub fn plugin_list(ctx: &ForgeContext, installed_plugin_path: PathBuf) -> Result<()> {
+    let plugins = collect_plugins(ctx, &installed_plugin_path)?;
+
+    ctx.output.emit(&plugins, |plugins, out| {
+        writeln!(out, "Plugins:")?;
+        writeln!(out, "{:<20} {:<10} {:<10} URL", "Name", "Version", "Installed")?;
+        writeln!(out, "{:<20} {:<10} {:<10} --------------------", "--------------------", "----------", "----------")?;
+        for plugin in plugins {
+            let version = plugin.version.as_deref().unwrap_or("unknown");
+            let installed = if plugin.installed { "✅" } else { "❌" };
+            let git_url = plugin.repo.as_deref().unwrap_or("N/A");
+            writeln!(out, "{:<20} {:<10} {:<10} {}", plugin.name, version, installed, git_url)?;
         }
-    }
+        Ok(())
+    })
+}
 
-    println!("Plugins:");
-    println!("{:<20} {:<10} {:<10} {}", "Name", "Version", "Installed", "URL");
-    println!("{:<20} {:<10} {:<10} {}", "--------------------", "----------", "----------", "--------------------");
-    for (name, details) in plugin_index.iter() {
-        let version = details.get("version").and_then(|v| v.as_str()).unwrap_or("unknown");
-        let installed = details.get("installed")
-            .and_then(|v| v.as_bool())
-            .map(|b| if b { "✅" } else { "❌" })
-            .unwrap_or("❌");
-        let git_url = details.get("repo").and_then(|v| v.as_str()).unwrap_or("N/A");
-        println!("{:<20} {:<10} {:<10} {}", name, version, installed, git_url);
-    }
+pub fn plugin_info(ctx: &ForgeContext, plugin_name: &str, installed_plugin_path: PathBuf) -> Result<()> {
+    let entry = collect_plugins(ctx, &installed_plugin_path)?
+        .into_iter()
+        .find(|p| p.name == plugin_name)
+        .with_context(|| format!("Plugin {} not found in plugin list", plugin_name))?;
 
-    Ok(())
+    let metadata = if entry.installed {
+        crate::plugin::plugin_commands::load_plugin_metadata(ctx, plugin_name).ok()
+    } else {
+        None
+    };
+
+    let engines = metadata.as_ref()
+        .map(|m| EngineRegistry::for_plugin(ctx, Some(m)).describe())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| e.source == EngineSource::Plugin)
+        .collect();
+
+    let info = PluginInfo { entry, metadata, engines };
+    ctx.output.emit(&info, |info, out| {
+        writeln!(out, "{:<12} {}", "Name:", info.entry.name)?;
+        writeln!(out, "{:<12} {}", "Version:", info.entry.version.as_deref().unwrap_or("unknown"))?;
+        writeln!(out, "{:<12} {}", "Description:", info.entry.description.as_deref().unwrap_or(""))?;
+        writeln!(out, "{:<12} {}", "Repo:", info.entry.repo.as_deref().unwrap_or("N/A"))?;
+        writeln!(out, "{:<12} {}", "Installed:", if info.entry.installed { "✅" } else { "❌" })?;
+        if info.entry.installed {
+            writeln!(out, "{:<12} {}", "Verified:", if info.entry.verified { "✅" } else { "❌" })?;
+        }
+        if let Some(path) = &info.entry.installed_path {
+            writeln!(out, "{:<12} {}", "Path:", path)?;
+        }
+        if let Some(metadata) = &info.metadata {
+            writeln!(out, "{:<12} {}", "Templates:", metadata.templates)?;
+        }
+        for engine in &info.engines {
+            let extensions = if engine.extensions.is_empty() {
+                "(catch-all)".to_string()
+            } else {
+                engine.extensions.join(", ")
+            };
+            writeln!(out, "{:<12} {} for {}", "Engine:", engine.name, extensions)?;
+        }
+        Ok(())
+    })
 }
 
-pub fn plugin_remove(plugin_name: &str, index_path: PathBuf) -> Result<()> {
+pub fn plugin_remove(ctx: &ForgeContext, plugin_name: &str, index_path: PathBuf, cascade: bool) -> Result<()> {
+    check_plugin_name(plugin_name)?;
     if !index_path.exists() {
         anyhow::bail!("Plugin index not found");
     }
 
-    let content = fs::read_to_string(&index_path)?;
-    let mut index: HashMap<String, serde_json::Value> = serde_json::from_str(&content)?;
+    let mut index = read_index(&index_path)?;
+    let change = remove_plugin(plugin_name, &mut index, cascade)?;
+    write_index(&index_path, &index)?;
+
+    ctx.output.emit(&change, |c, out| {
+        for dependent in &c.cascade {
+            writeln!(out, "Dependent plugin removed: {}", dependent.plugin)?;
+        }
+        writeln!(out, "Plugin removed: {}", c.plugin)?;
+        Ok(())
+    })
+}
+
+/// Remove a plugin, first removing installed plugins that depend on it when
+/// `cascade` is set and refusing otherwise.
+fn remove_plugin(plugin_name: &str, index: &mut HashMap<String, serde_json::Value>, cascade: bool) -> Result<PluginChange> {
+    let mut dependents: Vec<String> = index.iter()
+        .filter(|(_, entry)| is_installed(entry))
+        .filter(|(_, entry)| recorded_dependencies(entry).iter().any(|d| d == plugin_name))
+        .map(|(name, _)| name.clone())
+        .collect();
+    dependents.sort();
+
+    if !dependents.is_empty() && !cascade {
+        anyhow::bail!("Plugin {} is required by {}. Remove them first or pass --cascade.", plugin_name, dependents.join(", "));
+    }
+
+    let mut removed = Vec::new();
+    for dependent in dependents {
+        // An earlier cascade may already have removed this one
+        if index.get(&dependent).is_some_and(is_installed) {
+            removed.push(remove_plugin(&dependent, index, cascade)?);
+        }
+    }
 
     if let Some(plugin) = index.get_mut(plugin_name) {
         if let Some(path) = plugin.get("path").and_then(|v| v.as_str()) {
@@ -122,9 +473,65 @@ pub fn plugin_remove(plugin_name: &str, index_path: PathBuf) -> Result<()> {
         plugin["installed"] = serde_json::Value::Bool(false);
     }
 
-    let json = serde_json::to_string_pretty(&index)?;
-    fs::write(index_path, json)?;
+    Ok(PluginChange {
+        plugin: plugin_name.to_string(),
+        action: "removed",
+        path: None,
+        verification: None,
+        cascade: removed,
+    })
+}
 
-    println!("Plugin removed: {}", plugin_name);
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn install(registry: serde_json::Value, name: &str) -> (TempDir, Result<PluginChange>) {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let registry: HashMap<String, serde_json::Value> = serde_json::from_value(registry).unwrap();
+        let result = install_plugin(&ctx, name, &registry, &home.path().join("plugin-index.json"), &mut Vec::new());
+        (home, result)
+    }
+
+    #[test]
+    fn invalid_name_is_refused_before_touching_the_plugins_directory() {
+        let (home, result) = install(json!({}), "../outside");
+        assert!(result.unwrap_err().to_string().starts_with("Invalid plugin name '../outside'"));
+        assert!(!home.path().join("plugins").exists());
+    }
+
+    #[test]
+    fn plugin_list_entry_without_a_version_is_refused() {
+        let (home, result) = install(json!({ "demo": { "repo": "https://example.com/demo.git" } }), "demo");
+        assert_eq!(result.unwrap_err().to_string(), "Plugin demo has no version in the plugin list");
+        assert!(!home.path().join("plugins").exists());
+    }
+
+    #[test]
+    fn failed_dependency_removes_those_installed_before_it() {
+        let home = TempDir::new().unwrap();
+        let mut ctx = ForgeContext::for_tests(home.path());
+        ctx.allow_unverified = true;
+        let index_path = home.path().join("plugin-index.json");
+        let registry: HashMap<String, serde_json::Value> = serde_json::from_value(json!({
+            "base": { "version": "0.1.0" },
+            "lint": { "version": "1.0.0" },
+        })).unwrap();
+        let depends_on = BTreeMap::from([
+            ("base".to_string(), "*".to_string()),
+            ("lint".to_string(), "^2".to_string()),
+        ]);
+
+        let mut cascade = Vec::new();
+        let err = install_dependencies(&ctx, "app", &depends_on, &registry, &index_path, &mut vec!["app".to_string()], &mut cascade)
+            .unwrap_err();
+        assert_eq!(format!("{:#}", err), "Installing app failed; removed the dependencies installed for it again: base: \
+            Plugin app depends on lint ^2, but only 1.0.0 is available");
+        assert!(cascade.is_empty());
+        assert!(!read_index(&index_path).unwrap().contains_key("base"));
+        assert!(!ctx.paths.plugins_dir().join("base").exists());
+    }
 }
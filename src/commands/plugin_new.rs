@@ -0,0 +1,273 @@
+// src/commands/plugin_new.rs
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
+use anyhow::{Result, Context};
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use crate::context::ForgeContext;
+use crate::plugin::{check_plugin_name, is_plugin_name, PluginDetectRule, PluginMetadata, LIFECYCLE_STAGES};
+use crate::runtime_checker::is_package_name;
+use crate::verify::digest_dir;
+use crate::wasm_renderer::check_module;
+
+/// File that must exist in a project for the scaffolded plugin to be detected.
+const SAMPLE_DETECT_FILE: &str = "README.md";
+
+/// Result of `forge plugin new`.
+#[derive(Debug, Serialize)]
+pub struct PluginScaffold {
+    pub plugin: String,
+    pub path: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationProblem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Result of `forge plugin validate`.
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub path: PathBuf,
+    pub valid: bool,
+    pub problems: Vec<ValidationProblem>,
+}
+
+pub fn plugin_new(ctx: &ForgeContext, plugin_name: &str, dir: Option<PathBuf>) -> Result<()> {
+    check_plugin_name(plugin_name)?;
+    let plugin_dir = dir.unwrap_or_else(|| PathBuf::from(plugin_name));
+    if plugin_dir.exists() && fs::read_dir(&plugin_dir)?.next().is_some() {
+        anyhow::bail!("Directory {} already exists and is not empty", plugin_dir.display());
+    }
+
+    let metadata = PluginMetadata {
+        name: plugin_name.to_string(),
+        version: "0.1.0".to_string(),
+        description: Some(format!("Scaffolds {} projects.", plugin_name)),
+        templates: "templates".to_string(),
+        templates_sha256: None,
+        detect: Some(PluginDetectRule {
+            files: vec![SAMPLE_DETECT_FILE.to_string()],
+            ..Default::default()
+        }),
+        actions: Some(HashMap::from([
+            ("validate".to_string(), vec!["test -f README.md".to_string()]),
+            ("test".to_string(), vec!["echo \"no tests yet\"".to_string()]),
+        ])),
+        secrets: Vec::new(),
+        custom_renderer_command: None,
+        renderers: Vec::new(),
+        wasm_renderer: None,
+        runtimes: BTreeMap::new(),
+        prompts: BTreeMap::new(),
+        container: None,
+        depends_on: None,
+    };
+
+    let files: Vec<(PathBuf, String)> = vec![
+        (PathBuf::from("plugin.json"), serde_json::to_string_pretty(&metadata)? + "\n"),
+        (
+            PathBuf::from("templates/README.md"),
+            format!("# {{{{name}}}}\n\nGenerated by the {} forge plugin.\n", plugin_name),
+        ),
+        (
+            PathBuf::from("templates/.gitignore"),
+            "/target\n".to_string(),
+        ),
+        (
+            PathBuf::from("tests/fixture").join(SAMPLE_DETECT_FILE),
+            format!("# Fixture project detected by the {} plugin\n", plugin_name),
+        ),
+    ];
+
+    for (rel_path, content) in &files {
+        let path = plugin_dir.join(rel_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    let scaffold = PluginScaffold {
+        plugin: plugin_name.to_string(),
+        path: plugin_dir,
+        files: files.into_iter().map(|(p, _)| p).collect(),
+    };
+    ctx.output.emit(&scaffold, |s, out| {
+        for file in &s.files {
+            writeln!(out, "  created {}", s.path.join(file).display())?;
+        }
+        writeln!(out, "✅ Created plugin {} in {}. Check it with `forge plugin validate {}`.",
+            s.plugin, s.path.display(), s.path.display())?;
+        Ok(())
+    })
+}
+
+pub fn plugin_validate(ctx: &ForgeContext, plugin_dir: &Path) -> Result<()> {
+    let problems = validate_plugin_dir(plugin_dir);
+    let errors = problems.iter().filter(|p| p.severity == Severity::Error).count();
+
+    let report = ValidationReport {
+        path: plugin_dir.to_path_buf(),
+        valid: errors == 0,
+        problems,
+    };
+    ctx.output.emit(&report, |r, out| {
+        for problem in &r.problems {
+            let marker = match problem.severity {
+                Severity::Error => "✘ error:",
+                Severity::Warning => "⚠️  warning:",
+            };
+            writeln!(out, "{} {}", marker, problem.message)?;
+        }
+        if r.valid {
+            writeln!(out, "✅ Plugin {} is valid.", r.path.display())?;
+        }
+        Ok(())
+    })?;
+
+    if errors > 0 {
+        anyhow::bail!("Plugin validation failed with {} error(s)", errors);
+    }
+    Ok(())
+}
+
+fn validate_plugin_dir(plugin_dir: &Path) -> Vec<ValidationProblem> {
+    let mut problems = Vec::new();
+    let mut error = |message: String| problems.push(ValidationProblem { severity: Severity::Error, message });
+
+    let manifest_path = plugin_dir.join("plugin.json");
+    let content = match fs::read_to_string(&manifest_path) {
+        Ok(content) => content,
+        Err(err) => {
+            error(format!("cannot read {}: {}", manifest_path.display(), err));
+            return problems;
+        }
+    };
+    let metadata: PluginMetadata = match serde_json::from_str(&content) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error(format!("plugin.json is not valid plugin metadata: {}", err));
+            return problems;
+        }
+    };
+
+    if !is_plugin_name(&metadata.name) {
+        error(format!("name '{}' must be letters, digits, '-', '_' and '.', starting with a letter or digit", metadata.name));
+    }
+    if let Err(err) = Version::parse(&metadata.version) {
+        error(format!("version '{}' is not valid semver: {}", metadata.version, err));
+    }
+    for (runtime, constraint) in &metadata.runtimes {
+        if let Some(requirement) = &constraint.version
+            && let Err(err) = VersionReq::parse(requirement) {
+            error(format!("runtimes.{} has invalid version range '{}': {}", runtime, requirement, err));
+        }
+        for package in constraint.packages.iter().filter(|p| !is_package_name(p)) {
+            error(format!("runtimes.{}.packages has invalid package name '{}'", runtime, package));
+        }
+    }
+    for (dep, requirement) in metadata.depends_on.iter().flatten() {
+        if let Err(err) = VersionReq::parse(requirement) {
+            error(format!("depends_on.{} has invalid version range '{}': {}", dep, requirement, err));
+        }
+    }
+
+    let is_remote = metadata.templates.contains("://") || metadata.templates.starts_with("git@");
+    let templates_dir = plugin_dir.join(&metadata.templates);
+    if metadata.templates.is_empty() {
+        error("templates must name a bundled directory or a git URL".to_string());
+    } else if !is_remote && !templates_dir.is_dir() {
+        error(format!("templates directory {} does not exist", templates_dir.display()));
+    } else if !is_remote
+        && let Some(expected) = &metadata.templates_sha256
+        && let Ok(actual) = digest_dir(&templates_dir)
+        && !expected.eq_ignore_ascii_case(&actual) {
+        error(format!("templates_sha256 is {} but the templates hash to {}", expected, actual));
+    }
+
+    if let Some(command) = &metadata.custom_renderer_command
+        && command.split_whitespace().count() < 2 {
+        error("custom_renderer_command must be '<runtime> <script>'".to_string());
+    }
+    for (i, renderer) in metadata.renderers.iter().enumerate() {
+        for problem in renderer.problems() {
+            error(format!("renderers[{}]: {}", i, problem));
+        }
+    }
+    if let Some(wasm) = &metadata.wasm_renderer
+        && let Err(err) = check_module(&plugin_dir.join(&wasm.module)) {
+        error(format!("wasm_renderer: {:#}", err));
+    }
+    for (name, prompt) in &metadata.prompts {
+        for problem in prompt.problems(plugin_dir) {
+            error(format!("prompts.{}: {}", name, problem));
+        }
+    }
+    if let Some(container) = &metadata.container
+        && container.image.trim().is_empty() {
+        error("container.image must name an image".to_string());
+    }
+    for problem in metadata.detect.iter().flat_map(|rule| rule.pattern_problems()) {
+        error(format!("detect: {}", problem));
+    }
+    for name in &metadata.secrets {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') {
+            error(format!("secret '{}' must be an environment variable name (A-Z, 0-9 and _)", name));
+        }
+    }
+
+    let mut warning = |message: String| problems.push(ValidationProblem { severity: Severity::Warning, message });
+
+    if plugin_dir.file_name().is_some_and(|dir| dir.to_string_lossy() != metadata.name) {
+        warning(format!("directory name does not match plugin name '{}'", metadata.name));
+    }
+    if metadata.custom_renderer_command.is_some() {
+        warning("custom_renderer_command is deprecated; declare renderers instead".to_string());
+    }
+    if metadata.renderers.iter().filter(|r| r.is_catch_all()).count() > 1 {
+        warning("more than one renderer has no extensions; only the first is used for unclaimed files".to_string());
+    }
+    if metadata.description.as_deref().unwrap_or("").is_empty() {
+        warning("description is empty".to_string());
+    }
+
+    let mut stages: Vec<_> = metadata.actions.iter().flatten().collect();
+    stages.sort_by(|a, b| a.0.cmp(b.0));
+    for (stage, commands) in stages {
+        if !LIFECYCLE_STAGES.contains(&stage.as_str()) {
+            warning(format!("actions.{} is not a lifecycle stage ({})", stage, LIFECYCLE_STAGES.join(", ")));
+        }
+        if commands.is_empty() {
+            warning(format!("actions.{} has no commands", stage));
+        }
+    }
+
+    let fixture_dir = plugin_dir.join("tests/fixture");
+    match &metadata.detect {
+        None => warning("no detect rule; the plugin can only be used with --plugin".to_string()),
+        Some(rule) if rule.files.is_empty() && rule.any.is_empty() && rule.contains.is_empty() => {
+            warning("detect rule has no files, any or contains conditions and never matches".to_string());
+        }
+        Some(rule) if fixture_dir.is_dir() && !rule.matches(&fixture_dir) => {
+            warning(format!("detect rule does not match the fixture in {}", fixture_dir.display()));
+        }
+        Some(_) => {}
+    }
+    if !fixture_dir.is_dir() {
+        warning(format!("no test fixture at {}", fixture_dir.display()));
+    }
+
+    problems
+}
@@ -0,0 +1,63 @@
+// src/commands/run.rs
+use std::fmt::Write;
+use anyhow::{Result, Context};
+use clap::Args;
+use crate::context::ForgeContext;
+use crate::lifecycle::run_stage;
+use crate::manifest::ForgeManifest;
+use crate::plugin::plugin_commands::{detect_plugin, load_plugin_metadata, verify_installed_plugin};
+use crate::template_processor::load_variables;
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Print the commands without running them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Run in this component from .forge/manifest.json instead of the project root
+    #[arg(short, long)]
+    pub component: Option<String>,
+}
+
+/// `forge run <stage>` and the per-stage shortcuts (`forge test`, `forge build`, ...).
+pub fn run_lifecycle_stage(ctx: &ForgeContext, stage: &str, args: RunArgs) -> Result<()> {
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let manifest = ForgeManifest::load(&cwd)?;
+
+    let (project_dir, plugin) = match (&args.component, &manifest) {
+        (Some(name), Some(manifest)) => {
+            let component = manifest.components.iter()
+                .find(|c| &c.name == name || &c.path == name)
+                .with_context(|| format!("No component named {} in the manifest", name))?;
+            (cwd.join(&component.path), load_plugin_metadata(ctx, &component.plugin)?)
+        }
+        (Some(_), None) => anyhow::bail!("No .forge/manifest.json found. Run `forge init --recursive` first."),
+        (None, Some(manifest)) => (cwd.clone(), load_plugin_metadata(ctx, &manifest.plugin)?),
+        (None, None) => {
+            let plugin = detect_plugin(ctx, &cwd)
+                .context("Unable to detect plugin. Run `forge detect` to see why.")?;
+            (cwd.clone(), plugin)
+        }
+    };
+    verify_installed_plugin(ctx, &plugin.name)?;
+
+    let variables = load_variables(&cwd)?;
+    let secret_refs = manifest.map(|m| m.secrets).unwrap_or_default();
+    let report = run_stage(ctx, &plugin, stage, &project_dir, &variables, &secret_refs, args.dry_run)?;
+
+    ctx.output.emit(&report, |r, out| {
+        if r.dry_run {
+            return Ok(());
+        }
+        match r.exit_code() {
+            None => writeln!(out, "✅ {} finished ({} command(s)).", r.stage, r.actions.len())?,
+            Some(code) => writeln!(out, "✘ {} failed with exit code {}.", r.stage, code)?,
+        }
+        Ok(())
+    })?;
+
+    if let Some(code) = report.exit_code() {
+        std::process::exit(code);
+    }
+    Ok(())
+}
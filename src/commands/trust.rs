@@ -0,0 +1,52 @@
+// src/commands/trust.rs
+use std::fmt::Write;
+use anyhow::Result;
+use crate::context::ForgeContext;
+use serde::Serialize;
+use crate::trust::{TrustLevel, TrustStore};
+
+#[derive(Debug, Serialize)]
+pub struct TrustLevelChange {
+    pub plugin: String,
+    pub level: TrustLevel,
+}
+
+pub fn trust_list(ctx: &ForgeContext) -> Result<()> {
+    let store = TrustStore::load(ctx)?;
+    ctx.output.emit(&store.approved, |approved, out| {
+        if approved.is_empty() {
+            writeln!(out, "No plugin commands approved yet.")?;
+        }
+        for entry in approved {
+            writeln!(out, "{:<20} {:<10} {:<18} {}", entry.plugin, entry.version, entry.kind, entry.command)?;
+        }
+        Ok(())
+    })
+}
+
+/// Forget approvals for a plugin, optionally only for one version.
+pub fn trust_revoke(ctx: &ForgeContext, plugin: &str, version: Option<&str>) -> Result<()> {
+    let mut store = TrustStore::load(ctx)?;
+    let (revoked, kept): (Vec<_>, Vec<_>) = store.approved.into_iter()
+        .partition(|e| e.plugin == plugin && version.is_none_or(|v| e.version == v));
+    store.approved = kept;
+    store.save(ctx)?;
+
+    ctx.output.emit(&revoked, |revoked, out| writeln!(out, "Revoked {} approval(s) for {}.", revoked.len(), plugin))
+}
+
+pub fn trust_set(ctx: &ForgeContext, plugin: &str, level: TrustLevel) -> Result<()> {
+    let mut store = TrustStore::load(ctx)?;
+    store.levels.insert(plugin.to_string(), level);
+    store.save(ctx)?;
+
+    let change = TrustLevelChange { plugin: plugin.to_string(), level };
+    ctx.output.emit(&change, |c, out| {
+        let level = match c.level {
+            TrustLevel::Trusted => "trusted",
+            TrustLevel::Sandboxed => "sandboxed",
+        };
+        writeln!(out, "Plugin {} is now {}.", c.plugin, level)?;
+        Ok(())
+    })
+}
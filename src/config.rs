@@ -0,0 +1,398 @@
+// src/config.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::llm::{LlmConfig, LLM_MODEL_ENV, LLM_PROVIDER_ENV, LLM_URL_ENV};
+use crate::manifest::{manifest_path, ForgeManifest};
+use crate::paths::ForgePaths;
+use crate::render_agent::RENDER_AGENT_ENV;
+use crate::renderer::EngineSource;
+use crate::template_processor::ConflictPolicy;
+use crate::trust::{ExecPolicy, TrustLevel};
+
+/// Project configuration, relative to the project directory.
+pub const PROJECT_CONFIG: &str = ".forge/config.toml";
+
+/// Settings from the global `config.toml` (`~/.config/forge/config.toml` by
+/// default, see `paths`) and the project's `.forge/config.toml`.
+///
+/// Layers are applied in order: defaults, the global file, the project file,
+/// then environment variables; command-line flags win over all of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForgeConfig {
+    pub registry: RegistryConfig,
+    pub cache: CacheConfig,
+    pub generate: GenerateConfig,
+    pub engines: EnginesConfig,
+    pub llm: LlmConfig,
+    pub agent: AgentConfig,
+    pub trust: TrustConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Plugin lists, as paths (relative to the current directory) or http(s)
+    /// URLs. A plugin listed in several comes from the first.
+    pub urls: Vec<String>,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        RegistryConfig { urls: vec!["plugin-list.json".to_string()] }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Where downloaded plugin lists are kept; defaults to forge's cache directory.
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerateConfig {
+    /// What `forge generate` does with files that already exist (`--on-conflict`).
+    pub on_conflict: ConflictPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnginesConfig {
+    /// Which engine renders an extension that both a plugin and forge handle.
+    pub prefer: EngineSource,
+}
+
+impl Default for EnginesConfig {
+    fn default() -> Self {
+        EnginesConfig { prefer: EngineSource::Plugin }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Render agent that receives `FORGE_AGENT_TOKEN`; a project's
+    /// `render_agent` is used without it.
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrustConfig {
+    /// Unapproved plugin commands: `prompt`, `yes` (`--yes`) or `no-exec` (`--no-exec`).
+    pub exec: ExecPolicy,
+    /// Sandbox every plugin (`--sandbox`).
+    pub sandbox: bool,
+    /// Level of plugins `forge trust set` was not run for.
+    pub default_level: TrustLevel,
+}
+
+/// How a setting is written in the environment and on `forge config set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Text,
+    Bool,
+    /// Comma-separated.
+    List,
+}
+
+/// A setting `forge config` knows about.
+pub struct ConfigKey {
+    pub key: &'static str,
+    pub env: Option<&'static str>,
+    pub kind: ValueKind,
+    /// Only the global file and the environment may set it: a cloned
+    /// project must not approve commands, redirect the API key or serve its
+    /// own plugins.
+    pub global_only: bool,
+    pub help: &'static str,
+}
+
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey { key: "registry.urls", env: Some("FORGE_REGISTRY_URLS"), kind: ValueKind::List, global_only: true,
+        help: "Plugin lists (paths or http(s) URLs); the first listing a plugin wins" },
+    ConfigKey { key: "cache.dir", env: Some("FORGE_CACHE_DIR"), kind: ValueKind::Text, global_only: true,
+        help: "Cache for downloaded plugin lists (default: forge's cache directory)" },
+    ConfigKey { key: "generate.on_conflict", env: Some("FORGE_ON_CONFLICT"), kind: ValueKind::Text, global_only: false,
+        help: "overwrite, skip or fail when a generated file already exists" },
+    ConfigKey { key: "engines.prefer", env: Some("FORGE_ENGINES_PREFER"), kind: ValueKind::Text, global_only: false,
+        help: "plugin or builtin: which engine renders an extension both handle" },
+    ConfigKey { key: "llm.provider", env: Some(LLM_PROVIDER_ENV), kind: ValueKind::Text, global_only: false,
+        help: "openai or ollama" },
+    ConfigKey { key: "llm.url", env: Some(LLM_URL_ENV), kind: ValueKind::Text, global_only: true,
+        help: "Base URL of the LLM endpoint" },
+    ConfigKey { key: "llm.model", env: Some(LLM_MODEL_ENV), kind: ValueKind::Text, global_only: false,
+        help: "Model that writes AI-generated files" },
+    ConfigKey { key: "llm.api_key", env: None, kind: ValueKind::Text, global_only: true,
+        help: "Secret reference for the API key (pass:..., op://...), never the key" },
+    ConfigKey { key: "agent.url", env: Some(RENDER_AGENT_ENV), kind: ValueKind::Text, global_only: true,
+        help: "Render agent to send templates and FORGE_AGENT_TOKEN to" },
+    ConfigKey { key: "trust.exec", env: Some("FORGE_EXEC"), kind: ValueKind::Text, global_only: true,
+        help: "prompt, yes or no-exec for unapproved plugin commands" },
+    ConfigKey { key: "trust.sandbox", env: Some("FORGE_SANDBOX"), kind: ValueKind::Bool, global_only: true,
+        help: "Sandbox every plugin's renderers and hooks" },
+    ConfigKey { key: "trust.default_level", env: Some("FORGE_TRUST_LEVEL"), kind: ValueKind::Text, global_only: true,
+        help: "trusted or sandboxed, for plugins without a trust level" },
+];
+
+pub fn config_key(key: &str) -> Result<&'static ConfigKey> {
+    CONFIG_KEYS.iter().find(|k| k.key == key).with_context(|| format!(
+        "Unknown setting '{}'. Known settings: {}",
+        key, CONFIG_KEYS.iter().map(|k| k.key).collect::<Vec<_>>().join(", ")))
+}
+
+/// Where a layer comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env,
+}
+
+/// The layers of settings, lowest precedence first.
+pub struct ConfigLayers {
+    layers: Vec<(Source, Table)>,
+}
+
+impl ConfigLayers {
+    /// Read every layer for the project in `project_dir`.
+    pub fn load(paths: &ForgePaths, project_dir: &Path) -> Result<ConfigLayers> {
+        let mut layers = vec![(Source::Default, Table::try_from(ForgeConfig::default())?)];
+        let global = paths.global_config();
+        if let Some(mut table) = read_table(&global)? {
+            drop_unknown(&mut table, &global);
+            layers.push((Source::File(global), table));
+        }
+        // Before config files, the LLM was configured in the manifest
+        if let Some(llm) = ForgeManifest::load(project_dir)?.and_then(|m| m.llm) {
+            let mut table = Table::new();
+            table.insert("llm".to_string(), Value::try_from(llm)?);
+            let path = manifest_path(project_dir);
+            drop_unknown(&mut table, &path);
+            drop_global_only(&mut table, &path);
+            layers.push((Source::File(path), table));
+        }
+        let project = project_dir.join(PROJECT_CONFIG);
+        if let Some(mut table) = read_table(&project)? {
+            drop_unknown(&mut table, &project);
+            drop_global_only(&mut table, &project);
+            layers.push((Source::File(project), table));
+        }
+        layers.push((Source::Env, env_table()?));
+
+        for (source, table) in &layers {
+            Value::Table(table.clone()).try_into::<ForgeConfig>()
+                .with_context(|| format!("Invalid configuration in {}", source.describe(None)))?;
+        }
+        Ok(ConfigLayers { layers })
+    }
+
+    /// The settings all layers add up to.
+    pub fn merged(&self) -> Result<ForgeConfig> {
+        let mut merged = Table::new();
+        for (_, table) in &self.layers {
+            merge(&mut merged, table);
+        }
+        Ok(Value::Table(merged).try_into()?)
+    }
+
+    /// The value of a setting and the layer it comes from.
+    pub fn get(&self, key: &str) -> Option<(&Value, &Source)> {
+        self.layers.iter().rev()
+            .find_map(|(source, table)| lookup(table, key).map(|value| (value, source)))
+    }
+}
+
+impl Source {
+    /// `default`, the file, or the environment variable that set `key`.
+    pub fn describe(&self, key: Option<&ConfigKey>) -> String {
+        match self {
+            Source::Default => "default".to_string(),
+            Source::File(path) => path.display().to_string(),
+            Source::Env => match key.and_then(|k| k.env) {
+                Some(name) => format!("env {}", name),
+                None => "environment".to_string(),
+            },
+        }
+    }
+}
+
+pub fn read_table(path: &Path) -> Result<Option<Table>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    let table = content.parse::<Table>()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(table))
+}
+
+/// Parse `raw` as the value of `key`, as given in its environment variable or to `forge config set`.
+pub fn parse_value(key: &ConfigKey, raw: &str) -> Result<Value> {
+    let raw = raw.trim();
+    Ok(match key.kind {
+        ValueKind::Text => Value::String(raw.to_string()),
+        ValueKind::Bool => match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Value::Boolean(true),
+            "false" | "0" | "no" => Value::Boolean(false),
+            _ => anyhow::bail!("{} must be true or false, not '{}'", key.key, raw),
+        },
+        ValueKind::List => Value::Array(raw.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::String(item.to_string()))
+            .collect()),
+    })
+}
+
+/// Set the dotted `key` in `table`, creating tables on the way.
+pub fn insert(table: &mut Table, key: &str, value: Value) {
+    let (parents, last) = match key.rsplit_once('.') {
+        Some((parents, last)) => (parents.split('.').collect::<Vec<_>>(), last),
+        None => (Vec::new(), key),
+    };
+    let mut current = table;
+    for part in parents {
+        let entry = current.entry(part.to_string()).or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        current = entry.as_table_mut().expect("just made a table");
+    }
+    current.insert(last.to_string(), value);
+}
+
+/// Remove the dotted `key` from `table`, returning whether it was set.
+fn remove(table: &mut Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((first, rest)) => match table.get_mut(first) {
+            Some(Value::Table(inner)) => remove(inner, rest),
+            _ => false,
+        },
+        None => table.remove(key).is_some(),
+    }
+}
+
+/// Ignore the settings a project file may not make, with a warning.
+fn drop_global_only(table: &mut Table, path: &Path) {
+    for key in CONFIG_KEYS.iter().filter(|k| k.global_only) {
+        if remove(table, key.key) {
+            eprintln!("⚠️  Ignoring {} in {}: it can only be set in the global config.toml or the environment", key.key, path.display());
+        }
+    }
+}
+
+/// Ignore settings forge does not know, with a warning, so a typo or a key
+/// from a newer forge does not break every command.
+fn drop_unknown(table: &mut Table, path: &Path) {
+    for key in unknown_keys(table, "") {
+        remove(table, &key);
+        eprintln!("⚠️  Ignoring unknown setting {} in {}", key, path.display());
+    }
+}
+
+/// Dotted keys in `table` that are neither settings nor sections of them.
+fn unknown_keys(table: &Table, prefix: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    for (name, value) in table {
+        let key = format!("{}{}", prefix, name);
+        if CONFIG_KEYS.iter().any(|k| k.key == key) {
+            continue;
+        }
+        let section = format!("{}.", key);
+        match value {
+            Value::Table(inner) if CONFIG_KEYS.iter().any(|k| k.key.starts_with(&section)) => {
+                unknown.extend(unknown_keys(inner, &section));
+            }
+            _ => unknown.push(key),
+        }
+    }
+    unknown
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (first, rest) = match key.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (key, None),
+    };
+    match (table.get(first)?, rest) {
+        (Value::Table(inner), Some(rest)) => lookup(inner, rest),
+        (_, Some(_)) => None,
+        (value, None) => Some(value),
+    }
+}
+
+fn merge(into: &mut Table, from: &Table) {
+    for (key, value) in from {
+        match (into.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(inner)) => merge(existing, inner),
+            _ => {
+                into.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn env_table() -> Result<Table> {
+    let mut table = Table::new();
+    for key in CONFIG_KEYS {
+        let Some(name) = key.env else { continue };
+        let Some(raw) = std::env::var(name).ok().filter(|v| !v.trim().is_empty()) else { continue };
+        let value = parse_value(key, &raw).with_context(|| format!("Invalid {}", name))?;
+        // Check each variable alone, so an error names it
+        let mut single = Table::new();
+        insert(&mut single, key.key, value.clone());
+        Value::Table(single).try_into::<ForgeConfig>().with_context(|| format!("Invalid {}", name))?;
+        insert(&mut table, key.key, value);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn load(global: &str, project: &str) -> ForgeConfig {
+        let home = TempDir::new().unwrap();
+        let paths = ForgePaths { data: home.path().to_path_buf(), config: home.path().to_path_buf(), cache: home.path().join("cache") };
+        fs::write(paths.global_config(), global).unwrap();
+        let project_dir = home.path().join("project");
+        fs::create_dir_all(project_dir.join(".forge")).unwrap();
+        fs::write(project_dir.join(PROJECT_CONFIG), project).unwrap();
+        ConfigLayers::load(&paths, &project_dir).unwrap().merged().unwrap()
+    }
+
+    #[test]
+    fn project_cannot_redirect_registry_or_cache() {
+        let config = load(
+            "[registry]\nurls = [\"https://plugins.example.com/plugin-list.json\"]\n",
+            "[registry]\nurls = [\"https://evil.example.com/plugin-list.json\"]\n[cache]\ndir = \"/tmp/evil\"\n[generate]\non_conflict = \"skip\"\n",
+        );
+        assert_eq!(config.registry.urls, ["https://plugins.example.com/plugin-list.json"]);
+        assert_eq!(config.cache.dir, None);
+        assert_eq!(config.generate.on_conflict, ConflictPolicy::Skip);
+    }
+
+    #[test]
+    fn unknown_settings_are_ignored() {
+        let config = load(
+            "colour = true\n[generate]\non_conflict = \"fail\"\nparallel = 4\n[future]\nenabled = true\n",
+            "[llm]\nmodel = \"llama3.1\"\ntemperature = 0.2\n",
+        );
+        assert_eq!(config.generate.on_conflict, ConflictPolicy::Fail);
+        assert_eq!(config.llm.model.as_deref(), Some("llama3.1"));
+    }
+
+    #[test]
+    fn finds_unknown_keys_by_their_dotted_path() {
+        let table: Table = toml::from_str("colour = 1\n[llm]\nurl = \"x\"\nseed = 1\n[trust.extra]\nx = 1\n").unwrap();
+        assert_eq!(unknown_keys(&table, ""), ["colour", "llm.seed", "trust.extra"]);
+    }
+}
@@ -0,0 +1,250 @@
+// src/container.rs
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::TempDir;
+
+use crate::plugin::PluginMetadata;
+use crate::renderer::{Capabilities, RenderScript, Renderer, Requirement};
+use crate::runtime_checker::program_exists;
+
+/// Overrides the container runtime; any program taking `docker run` arguments works.
+pub const CONTAINER_RUNTIME_ENV: &str = "FORGE_CONTAINER_RUNTIME";
+
+/// Where the mounts appear inside the container.
+const TEMPLATE_MOUNT: &str = "/forge/templates";
+const OUTPUT_MOUNT: &str = "/forge/output";
+const JOB_MOUNT: &str = "/forge/job";
+
+/// A plugin's `container`: an image with the runtimes its templates need,
+/// used when they are missing on the host.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContainerSpec {
+    pub image: String,
+    /// Runtimes the image provides; empty means every built-in engine's runtime.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runtimes: Vec<String>,
+}
+
+impl ContainerSpec {
+    pub fn provides(&self, runtime: &str) -> bool {
+        self.runtimes.is_empty() || self.runtimes.iter().any(|r| r == runtime)
+    }
+}
+
+/// The plugin's container, when it should run `runtime` because the host lacks it.
+pub fn container_for<'a>(plugin: Option<&'a PluginMetadata>, runtime: &str) -> Option<&'a ContainerSpec> {
+    plugin?.container.as_ref()
+        .filter(|spec| spec.provides(runtime))
+        .filter(|_| !program_exists(runtime, None))
+}
+
+/// The container runtime to use: `$FORGE_CONTAINER_RUNTIME`, else docker, else podman.
+pub fn container_runtime() -> Option<String> {
+    if let Ok(runtime) = std::env::var(CONTAINER_RUNTIME_ENV)
+        && !runtime.trim().is_empty() {
+        return Some(runtime);
+    }
+    ["docker", "podman"].into_iter()
+        .find(|program| program_exists(program, None))
+        .map(str::to_string)
+}
+
+/// A built-in engine whose runtime runs in the plugin's container image.
+///
+/// Only the template's directory (read-only), the output file's directory and
+/// a job directory holding the render script and variables are mounted; the
+/// container has no network.
+pub struct ContainerEngine<'a> {
+    pub extension: &'static str,
+    pub runtime: &'static str,
+    pub script: RenderScript,
+    pub spec: &'a ContainerSpec,
+    /// The container runtime; `None` means `container_runtime()`.
+    pub program: Option<String>,
+}
+
+impl ContainerEngine<'_> {
+    fn program(&self) -> String {
+        self.program.clone()
+            .or_else(container_runtime)
+            .unwrap_or_else(|| "docker".to_string())
+    }
+
+    fn run_args(&self, template_dir: &Path, output_dir: &Path, job_dir: &Path, file_name: &str, output_name: &str) -> Result<Vec<String>> {
+        // `--mount` takes comma-separated fields, which podman does not let us quote
+        let mount = |host: &Path, target: &str, read_only: bool| {
+            let source = host.display().to_string();
+            if source.contains([',', '\n', '\r']) {
+                anyhow::bail!("Cannot mount {:?} in a container: its path contains a comma or line break", host);
+            }
+            Ok(format!("type=bind,source={},target={}{}", source, target, if read_only { ",readonly" } else { "" }))
+        };
+        let mut args = vec![
+            "run".to_string(),
+            "--rm".to_string(),
+            "--network".to_string(),
+            "none".to_string(),
+            "--mount".to_string(),
+            mount(template_dir, TEMPLATE_MOUNT, true)?,
+            "--mount".to_string(),
+            mount(output_dir, OUTPUT_MOUNT, false)?,
+            "--mount".to_string(),
+            mount(job_dir, JOB_MOUNT, true)?,
+            "--workdir".to_string(),
+            OUTPUT_MOUNT.to_string(),
+        ];
+        // Keep generated files owned by the user rather than root
+        #[cfg(unix)]
+        if let Ok(meta) = fs::metadata(output_dir) {
+            use std::os::unix::fs::MetadataExt;
+            args.push("--user".to_string());
+            args.push(format!("{}:{}", meta.uid(), meta.gid()));
+        }
+        args.extend([
+            self.spec.image.clone(),
+            self.runtime.to_string(),
+            format!("{}/{}", JOB_MOUNT, self.script.name),
+            format!("{}/{}", TEMPLATE_MOUNT, file_name),
+            format!("{}/{}", OUTPUT_MOUNT, output_name),
+            format!("{}/variables.json", JOB_MOUNT),
+        ]);
+        Ok(args)
+    }
+}
+
+impl Renderer for ContainerEngine<'_> {
+    fn name(&self) -> String {
+        format!("{}@{}", self.extension, self.spec.image)
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        vec![self.extension.to_string()]
+    }
+
+    fn requirements(&self) -> Vec<Requirement> {
+        vec![
+            Requirement::Program { name: self.program(), cwd: None },
+        ]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        // The image comes from the plugin
+        Capabilities { runs_plugin_code: true, ..Capabilities::default() }
+    }
+
+    fn approval_command(&self) -> String {
+        format!("{} run --network none {} {}", self.program(), self.spec.image, self.runtime)
+    }
+
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        let input_path = std::path::absolute(input_path)?;
+        let output_path = std::path::absolute(output_path)?;
+        let (Some(template_dir), Some(file_name), Some(output_dir), Some(output_name)) = (
+            input_path.parent(),
+            input_path.file_name(),
+            output_path.parent(),
+            output_path.file_name(),
+        ) else {
+            anyhow::bail!("Cannot render {:?} in a container: not a file path", input_path);
+        };
+
+        let job_dir = TempDir::new()?;
+        self.script.write_to(job_dir.path())?;
+        let mut vars_file = fs::File::create(job_dir.path().join("variables.json"))?;
+        vars_file.write_all(serde_json::to_string(variables)?.as_bytes())?;
+
+        let program = self.program();
+        let args = self.run_args(template_dir, output_dir, job_dir.path(),
+            &file_name.to_string_lossy(), &output_name.to_string_lossy())?;
+        let status = Command::new(&program)
+            .args(&args)
+            .status()
+            .with_context(|| format!("Failed to run {}. Is it installed?", program))?;
+
+        if !status.success() {
+            anyhow::bail!("Rendering failed using {} in container {} for: {:?}", self.runtime, self.spec.image, input_path);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Stands in for docker: records its arguments next to itself and writes
+    /// the output file through the output mount, failing for `fail.*` templates.
+    const STUB_RUNTIME: &str = r#"#!/bin/sh
+printf '%s\n' "$@" > "$(dirname "$0")/args.txt"
+for arg; do
+  case "$arg" in
+    type=bind,source=*,target=/forge/output) out=${arg#type=bind,source=}; out=${out%,target=/forge/output} ;;
+  esac
+done
+eval "template=\${$(($# - 2))}"
+eval "output=\${$(($# - 1))}"
+case "$template" in */fail.*) exit 3 ;; esac
+echo "rendered ${template##*/}" > "$out/${output#/forge/output/}"
+"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn render_runs_the_configured_container_runtime() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let work = TempDir::new().unwrap();
+        let stub = work.path().join("fake-docker");
+        fs::write(&stub, STUB_RUNTIME).unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let templates = work.path().join("templates");
+        let output = work.path().join("output");
+        fs::create_dir_all(&templates).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(templates.join("README.md.erb"), "<%= name %>").unwrap();
+        fs::write(templates.join("fail.erb"), "").unwrap();
+
+        let spec = ContainerSpec { image: "ruby:3.3".to_string(), runtimes: Vec::new() };
+        let engine = ContainerEngine {
+            extension: "erb",
+            runtime: "ruby",
+            script: RenderScript { name: "render_erb.rb", source: "# stub" },
+            spec: &spec,
+            program: Some(stub.display().to_string()),
+        };
+        assert_eq!(engine.requirements(), vec![Requirement::Program { name: stub.display().to_string(), cwd: None }]);
+
+        engine.render(&templates.join("README.md.erb"), &output.join("README.md"), &json!({ "name": "demo" })).unwrap();
+        assert_eq!(fs::read_to_string(output.join("README.md")).unwrap(), "rendered README.md.erb\n");
+
+        let args = fs::read_to_string(work.path().join("args.txt")).unwrap();
+        let args: Vec<&str> = args.lines().collect();
+        assert_eq!(args[..4], ["run", "--rm", "--network", "none"]);
+        assert!(args.contains(&format!("type=bind,source={},target={},readonly", templates.display(), TEMPLATE_MOUNT).as_str()));
+        assert!(args.contains(&format!("type=bind,source={},target={}", output.display(), OUTPUT_MOUNT).as_str()));
+        assert!(args.contains(&"--user"));
+        assert_eq!(args[args.len() - 6..], [
+            "ruby:3.3",
+            "ruby",
+            "/forge/job/render_erb.rb",
+            "/forge/templates/README.md.erb",
+            "/forge/output/README.md",
+            "/forge/job/variables.json",
+        ]);
+
+        let err = engine.render(&templates.join("fail.erb"), &output.join("fail"), &json!({})).unwrap_err();
+        assert!(err.to_string().contains("in container ruby:3.3"), "{}", err);
+
+        let odd = work.path().join("a,b");
+        fs::create_dir_all(&odd).unwrap();
+        fs::write(odd.join("x.erb"), "").unwrap();
+        let err = engine.render(&odd.join("x.erb"), &output.join("x"), &json!({})).unwrap_err();
+        assert!(err.to_string().contains("contains a comma"), "{}", err);
+    }
+}
@@ -0,0 +1,51 @@
+// src/context.rs
+use std::path::PathBuf;
+
+use crate::config::ForgeConfig;
+use crate::llm_cache::LlmMode;
+use crate::output::OutputFormat;
+use crate::paths::ForgePaths;
+use crate::trust::ExecPolicy;
+
+/// What a command runs with: forge's directories, the merged settings and the
+/// global flags. Built once in `main` and passed down.
+#[derive(Debug, Clone)]
+pub struct ForgeContext {
+    pub paths: ForgePaths,
+    pub config: ForgeConfig,
+    pub output: OutputFormat,
+    /// `--allow-unverified`: use plugins and templates that fail verification.
+    pub allow_unverified: bool,
+    /// `--locked`: fail instead of updating `.forge/forge.lock`.
+    pub locked: bool,
+    /// `--yes` or `--no-exec`, else `trust.exec`.
+    pub exec: ExecPolicy,
+    /// Sandbox every plugin regardless of its trust level.
+    pub sandbox: bool,
+    pub llm: LlmMode,
+}
+
+impl ForgeContext {
+    /// `cache.dir`, or forge's cache directory.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.config.cache.dir.clone().unwrap_or_else(|| self.paths.cache.clone())
+    }
+}
+
+#[cfg(test)]
+impl ForgeContext {
+    /// Default settings with every forge directory under `home`, never
+    /// running plugin commands or asking the LLM.
+    pub fn for_tests(home: &std::path::Path) -> ForgeContext {
+        ForgeContext {
+            paths: ForgePaths { data: home.to_path_buf(), config: home.to_path_buf(), cache: home.join("cache") },
+            config: ForgeConfig::default(),
+            output: OutputFormat::Json,
+            allow_unverified: false,
+            locked: false,
+            exec: ExecPolicy::NoExec,
+            sandbox: false,
+            llm: LlmMode::Off,
+        }
+    }
+}
@@ -0,0 +1,473 @@
+// src/detect.rs
+use std::fs;
+use std::path::{Component as PathComponent, Path, PathBuf};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::manifest::Component;
+use crate::plugin::PluginMetadata;
+
+/// How many directory levels below the project root `find_components` searches.
+pub const DEFAULT_SCAN_DEPTH: usize = 3;
+
+/// Directories that never hold components of their own.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "venv", "__pycache__"];
+
+/// Score contributed by each kind of satisfied condition. Content matches are
+/// the most specific signal, so they outweigh file presence.
+const FILE_SCORE: u32 = 10;
+const ANY_SCORE: u32 = 5;
+const CONTENT_SCORE: u32 = 20;
+
+/// Detection rule declared in a plugin's `detect` section.
+///
+/// Patterns are paths relative to the project root and may be globs
+/// (`*.tf`, `**/*.py`); absolute patterns and `..` are rejected. A rule needs at least one positive condition
+/// (`files`, `any` or `contains`) to ever match.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PluginDetectRule {
+    /// Every pattern must match at least one path.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// At least one pattern must match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<String>,
+    /// No pattern may match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub none: Vec<String>,
+    /// Files whose contents must match.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contains: Vec<ContentMatch>,
+    /// Added to the score of a matching rule to favour this plugin over
+    /// overlapping ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+/// Match on file contents: a substring (`text`) or a regular expression
+/// (`regex`, in multi-line mode so `^` and `$` match at each line).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContentMatch {
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+/// Outcome of a single condition of a detect rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleCheck {
+    pub rule: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// How well one plugin's detect rule fits a project.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub plugin: String,
+    pub version: String,
+    pub matched: bool,
+    pub score: u32,
+    pub checks: Vec<RuleCheck>,
+}
+
+/// All candidates for a project, best first, and the one selected.
+#[derive(Debug, Serialize)]
+pub struct Detection {
+    pub path: PathBuf,
+    pub candidates: Vec<Candidate>,
+    pub selected: Option<String>,
+    /// Other plugins that matched with the same score as `selected`.
+    pub ties: Vec<String>,
+}
+
+impl PluginDetectRule {
+    /// True when the rule matches the project at `path`.
+    pub fn matches(&self, path: &Path) -> bool {
+        let (matched, _, _) = self.evaluate(path);
+        matched
+    }
+
+    /// Patterns that are absolute or leave the project with `..`, which never
+    /// match and make the rule fail.
+    pub fn pattern_problems(&self) -> Vec<String> {
+        self.files.iter().chain(&self.any).chain(&self.none)
+            .chain(self.contains.iter().map(|c| &c.file))
+            .filter(|pattern| !is_project_pattern(pattern))
+            .map(|pattern| format!("pattern {:?} must be relative and stay inside the project", pattern))
+            .collect()
+    }
+
+    /// Evaluate every condition, returning whether the rule matched, its score
+    /// and the per-condition results.
+    pub fn evaluate(&self, path: &Path) -> (bool, u32, Vec<RuleCheck>) {
+        let mut checks: Vec<RuleCheck> = self.pattern_problems().into_iter()
+            .map(|detail| RuleCheck { rule: "detect".to_string(), passed: false, detail })
+            .collect();
+        let mut score = 0;
+
+        for pattern in &self.files {
+            let found = find_paths(path, pattern);
+            if !found.is_empty() {
+                score += FILE_SCORE;
+            }
+            checks.push(RuleCheck {
+                rule: format!("files: {}", pattern),
+                passed: !found.is_empty(),
+                detail: describe_found(&found, path),
+            });
+        }
+
+        if !self.any.is_empty() {
+            let found: Vec<&String> = self.any.iter()
+                .filter(|p| !find_paths(path, p).is_empty())
+                .collect();
+            score += ANY_SCORE * found.len() as u32;
+            checks.push(RuleCheck {
+                rule: format!("any: {}", self.any.join(", ")),
+                passed: !found.is_empty(),
+                detail: if found.is_empty() {
+                    "none of the patterns matched".to_string()
+                } else {
+                    format!("matched {}", found.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", "))
+                },
+            });
+        }
+
+        for pattern in &self.none {
+            let found = find_paths(path, pattern);
+            checks.push(RuleCheck {
+                rule: format!("none: {}", pattern),
+                passed: found.is_empty(),
+                detail: if found.is_empty() {
+                    "not present".to_string()
+                } else {
+                    format!("unexpectedly found {}", describe_found(&found, path))
+                },
+            });
+        }
+
+        for content in &self.contains {
+            let check = content.evaluate(path);
+            if check.passed {
+                score += CONTENT_SCORE;
+            }
+            checks.push(check);
+        }
+
+        let has_positive = !self.files.is_empty() || !self.any.is_empty() || !self.contains.is_empty();
+        if !has_positive {
+            checks.push(RuleCheck {
+                rule: "detect".to_string(),
+                passed: false,
+                detail: "rule has no files, any or contains conditions".to_string(),
+            });
+        }
+
+        let matched = checks.iter().all(|c| c.passed);
+        if matched {
+            score += self.priority.unwrap_or(0);
+        } else {
+            score = 0;
+        }
+        (matched, score, checks)
+    }
+}
+
+enum Matcher {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, content: &str) -> bool {
+        match self {
+            Matcher::Text(text) => content.contains(text.as_str()),
+            Matcher::Regex(re) => re.is_match(content),
+        }
+    }
+}
+
+impl ContentMatch {
+    fn evaluate(&self, path: &Path) -> RuleCheck {
+        let (rule, matcher) = match (&self.text, &self.regex) {
+            (_, Some(pattern)) => {
+                let rule = format!("contains: {} =~ /{}/", self.file, pattern);
+                match RegexBuilder::new(pattern).multi_line(true).build() {
+                    Ok(re) => (rule, Matcher::Regex(re)),
+                    Err(err) => return RuleCheck { rule, passed: false, detail: format!("invalid regex: {}", err) },
+                }
+            }
+            (Some(text), None) => (format!("contains: {} has {:?}", self.file, text), Matcher::Text(text.clone())),
+            (None, None) => {
+                return RuleCheck {
+                    rule: format!("contains: {}", self.file),
+                    passed: false,
+                    detail: "needs `text` or `regex`".to_string(),
+                };
+            }
+        };
+
+        let found = find_paths(path, &self.file);
+        if found.is_empty() {
+            return RuleCheck { rule, passed: false, detail: "file not found".to_string() };
+        }
+        let hit = found.iter()
+            .find(|p| fs::read_to_string(p).is_ok_and(|content| matcher.is_match(&content)));
+        match hit {
+            Some(p) => RuleCheck { rule, passed: true, detail: format!("matched in {}", relative(p, path)) },
+            None => RuleCheck { rule, passed: false, detail: format!("no match in {}", describe_found(&found, path)) },
+        }
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Is `pattern` relative and free of `..`, so it can only match inside the project?
+fn is_project_pattern(pattern: &str) -> bool {
+    let path = Path::new(pattern);
+    !pattern.starts_with(['/', '\\'])
+        && path.components().all(|c| matches!(c, PathComponent::Normal(_) | PathComponent::CurDir))
+}
+
+/// Paths below `root` matching `pattern`, sorted; nothing for patterns that
+/// could leave `root`.
+fn find_paths(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    if !is_project_pattern(pattern) {
+        return Vec::new();
+    }
+    if !is_glob(pattern) {
+        let candidate = root.join(pattern);
+        return if candidate.exists() { vec![candidate] } else { Vec::new() };
+    }
+
+    // The root is matched literally, even if its name holds `*`, `?` or `[`
+    let full = format!("{}/{}", glob::Pattern::escape(&root.to_string_lossy()), pattern);
+    let mut found: Vec<PathBuf> = glob::glob(&full)
+        .map(|paths| paths.filter_map(|p| p.ok()).collect())
+        .unwrap_or_default();
+    found.sort();
+    found
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+fn describe_found(found: &[PathBuf], root: &Path) -> String {
+    match found {
+        [] => "not found".to_string(),
+        [one] => format!("found {}", relative(one, root)),
+        [first, rest @ ..] => format!("found {} and {} more", relative(first, root), rest.len()),
+    }
+}
+
+/// Evaluate every plugin against the project at `path` and pick the best match.
+///
+/// Candidates are ordered by match, then score, then name, so the selection
+/// does not depend on index order.
+pub fn rank_plugins(path: &Path, plugins: &[PluginMetadata]) -> Detection {
+    let mut candidates: Vec<Candidate> = plugins.iter()
+        .map(|plugin| {
+            let (matched, score, checks) = match &plugin.detect {
+                Some(rule) => rule.evaluate(path),
+                None => (false, 0, vec![RuleCheck {
+                    rule: "detect".to_string(),
+                    passed: false,
+                    detail: "plugin declares no detect rule".to_string(),
+                }]),
+            };
+            Candidate {
+                plugin: plugin.name.clone(),
+                version: plugin.version.clone(),
+                matched,
+                score,
+                checks,
+            }
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        b.matched.cmp(&a.matched)
+            .then(b.score.cmp(&a.score))
+            .then(a.plugin.cmp(&b.plugin))
+    });
+
+    let best = candidates.first().filter(|c| c.matched);
+    let selected = best.map(|c| c.plugin.clone());
+    let ties = best
+        .map(|best| candidates.iter()
+            .skip(1)
+            .filter(|c| c.matched && c.score == best.score)
+            .map(|c| c.plugin.clone())
+            .collect())
+        .unwrap_or_default();
+
+    Detection { path: path.to_path_buf(), candidates, selected, ties }
+}
+
+/// Find every directory below `root` that some plugin detects, for projects
+/// made of several components (e.g. a Terraform module with a Python Lambda
+/// in `lambda/`).
+///
+/// A directory whose best match is the same plugin as an enclosing component
+/// is treated as part of that component rather than a new one.
+pub fn find_components(root: &Path, plugins: &[PluginMetadata], max_depth: usize) -> Vec<Component> {
+    let mut components: Vec<Component> = Vec::new();
+    let dirs = WalkDir::new(root)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir());
+
+    for dir in dirs {
+        let Some(plugin) = rank_plugins(dir.path(), plugins).selected else {
+            continue;
+        };
+        let rel_path = dir.path().strip_prefix(root).unwrap_or(dir.path());
+        let path = if rel_path.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            rel_path.to_string_lossy().replace('\\', "/")
+        };
+
+        let enclosed = components.iter()
+            .any(|c| c.plugin == plugin && (c.path == "." || path.starts_with(&format!("{}/", c.path))));
+        if enclosed {
+            continue;
+        }
+
+        let name = if path == "." { "root".to_string() } else { path.replace('/', "-") };
+        components.push(Component { name, path, plugin });
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_support::{self, tree as project};
+
+    fn plugin(name: &str, detect: serde_json::Value) -> PluginMetadata {
+        test_support::plugin(name, json!({ "detect": detect }))
+    }
+
+    fn score(detection: &Detection, plugin: &str) -> u32 {
+        detection.candidates.iter().find(|c| c.plugin == plugin).unwrap().score
+    }
+
+    #[test]
+    fn scores_add_up_per_condition() {
+        let dir = project(&[("Cargo.toml", "[package]\nname = \"demo\""), ("src/main.rs", ""), ("build.rs", "")]);
+        let plugins = [
+            plugin("files", json!({ "files": ["Cargo.toml", "src/*.rs"] })),
+            plugin("any", json!({ "any": ["build.rs", "Cargo.toml", "Makefile"] })),
+            plugin("contains", json!({ "contains": [{ "file": "Cargo.toml", "text": "[package]" }] })),
+            plugin("priority", json!({ "files": ["Cargo.toml"], "priority": 7 })),
+        ];
+
+        let detection = rank_plugins(dir.path(), &plugins);
+        assert_eq!(score(&detection, "files"), 2 * FILE_SCORE);
+        assert_eq!(score(&detection, "any"), 2 * ANY_SCORE);
+        assert_eq!(score(&detection, "contains"), CONTENT_SCORE);
+        assert_eq!(score(&detection, "priority"), FILE_SCORE + 7);
+        // 20 each: the name decides, and the other is reported as a tie
+        assert_eq!(detection.selected.as_deref(), Some("contains"));
+        assert_eq!(detection.ties, vec!["files"]);
+    }
+
+    #[test]
+    fn failed_condition_scores_zero() {
+        let dir = project(&[("Cargo.toml", ""), ("package.json", "{}")]);
+        let plugins = [
+            plugin("rust", json!({ "files": ["Cargo.toml"], "none": ["package.json"] })),
+            plugin("missing", json!({ "files": ["Cargo.toml", "go.mod"] })),
+            plugin("empty", json!({ "none": ["go.mod"] })),
+        ];
+
+        let detection = rank_plugins(dir.path(), &plugins);
+        assert!(detection.candidates.iter().all(|c| !c.matched && c.score == 0));
+        assert_eq!(detection.selected, None);
+        assert!(detection.ties.is_empty());
+    }
+
+    #[test]
+    fn ties_are_broken_by_name_and_reported() {
+        let dir = project(&[("main.tf", "")]);
+        let plugins = [
+            plugin("terraform-b", json!({ "files": ["*.tf"] })),
+            plugin("terraform-a", json!({ "files": ["*.tf"] })),
+            plugin("terraform-c", json!({ "files": ["*.tf"], "priority": 1 })),
+        ];
+
+        let detection = rank_plugins(dir.path(), &plugins);
+        assert_eq!(detection.selected.as_deref(), Some("terraform-c"));
+        assert!(detection.ties.is_empty());
+
+        let detection = rank_plugins(dir.path(), &plugins[..2]);
+        assert_eq!(detection.selected.as_deref(), Some("terraform-a"));
+        assert_eq!(detection.ties, vec!["terraform-b"]);
+    }
+
+    #[test]
+    fn content_beats_file_presence() {
+        let dir = project(&[("Cargo.toml", "[package]\n\n[workspace]\nmembers = []")]);
+        let plugins = [
+            plugin("rust", json!({ "files": ["Cargo.toml"] })),
+            plugin("rust-workspace", json!({ "contains": [{ "file": "Cargo.toml", "regex": "^\\[workspace\\]$" }] })),
+        ];
+
+        let detection = rank_plugins(dir.path(), &plugins);
+        assert_eq!(detection.selected.as_deref(), Some("rust-workspace"));
+        let names: Vec<&str> = detection.candidates.iter().map(|c| c.plugin.as_str()).collect();
+        assert_eq!(names, ["rust-workspace", "rust"]);
+    }
+
+    #[test]
+    fn invalid_regex_does_not_match() {
+        let dir = project(&[("Cargo.toml", "")]);
+        let detection = rank_plugins(dir.path(), &[plugin("bad", json!({ "contains": [{ "file": "Cargo.toml", "regex": "(" }] }))]);
+        let check = &detection.candidates[0].checks[0];
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("invalid regex"), "{}", check.detail);
+    }
+
+    #[test]
+    fn project_path_with_glob_characters_is_matched_literally() {
+        let parent = project(&[("app[1]/main.tf", ""), ("app1/other.tf", "")]);
+        let root = parent.path().join("app[1]");
+        let detection = rank_plugins(&root, &[plugin("terraform", json!({ "files": ["*.tf"] }))]);
+        assert_eq!(detection.selected.as_deref(), Some("terraform"));
+        assert_eq!(detection.candidates[0].checks[0].detail, "found main.tf");
+    }
+
+    #[test]
+    fn patterns_leaving_the_project_fail_the_rule() {
+        let outer = project(&[("secret.txt", "token"), ("proj/Cargo.toml", "")]);
+        let root = outer.path().join("proj");
+        let absolute = outer.path().join("secret.txt").display().to_string();
+        for detect in [
+            json!({ "files": ["Cargo.toml", "../secret.txt"] }),
+            json!({ "files": ["Cargo.toml"], "any": [absolute] }),
+            json!({ "files": ["Cargo.toml"], "none": ["../*.txt"] }),
+            json!({ "contains": [{ "file": "../secret.txt", "text": "token" }] }),
+        ] {
+            let detection = rank_plugins(&root, &[plugin("sneaky", detect.clone())]);
+            assert_eq!(detection.selected, None, "{}", detect);
+            let check = &detection.candidates[0].checks[0];
+            assert!(!check.passed && check.detail.ends_with("must be relative and stay inside the project"), "{}", detect);
+        }
+        assert!(is_project_pattern("src/**/*.rs") && is_project_pattern("./Cargo.toml"));
+    }
+}
@@ -0,0 +1,178 @@
+// src/hooks.rs
+use std::io::Read;
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::context::ForgeContext;
+use crate::lifecycle::action_env;
+use crate::plugin::PluginMetadata;
+use crate::secrets::redact;
+use crate::sandbox::{sandboxed_command, SandboxSpec};
+use crate::template_manifest::{Hook, DEFAULT_HOOK_TIMEOUT_SECS};
+use crate::trust::ensure_trusted;
+
+/// Outcome of a template hook.
+#[derive(Debug, Serialize)]
+pub struct HookResult {
+    pub stage: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u128,
+    /// Not run because of `--no-exec`.
+    pub skipped: bool,
+}
+
+/// Run one hook in `output_dir` with the template variables in its environment,
+/// sandboxed when the plugin's trust level asks for it.
+///
+/// The hook's stdout is captured: when it is a JSON object, its keys are
+/// merged into `variables` so pre-generate hooks can compute derived values.
+/// Anything else is echoed to stderr.
+pub fn run_hook(
+    ctx: &ForgeContext,
+    hook: &Hook,
+    stage: &str,
+    plugin: &PluginMetadata,
+    template_root: &Path,
+    output_dir: &Path,
+    variables: &mut Value,
+) -> Result<HookResult> {
+    let timeout = Duration::from_secs(hook.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS));
+    let started = Instant::now();
+
+    if !ensure_trusted(ctx, plugin, &format!("hook:{}", stage), &hook.command, &[template_root, output_dir])? {
+        return Ok(HookResult {
+            stage: stage.to_string(),
+            command: hook.command.clone(),
+            exit_code: None,
+            duration_ms: 0,
+            skipped: true,
+        });
+    }
+
+    eprintln!("▶ {} hook: {}", stage, redact(&hook.command));
+    // Sandboxed hooks may read the templates and change only the output directory
+    let spec = SandboxSpec {
+        read_only: vec![template_root.to_path_buf()],
+        writable: vec![output_dir.to_path_buf()],
+        workdir: Some(output_dir.to_path_buf()),
+    };
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = sandboxed_command(ctx, Some(plugin), shell, &[flag, &hook.command], &spec)?
+        .envs(action_env(plugin, stage, output_dir, variables))
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {} hook '{}'", stage, hook.command))?;
+
+    // Drain stdout on a thread so a chatty hook cannot block on a full pipe
+    let mut stdout = child.stdout.take().context("Hook stdout unavailable")?;
+    let reader = thread::spawn(move || {
+        let mut captured = String::new();
+        stdout.read_to_string(&mut captured).map(|_| captured)
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() > timeout {
+            child.kill().ok();
+            child.wait().ok();
+            reader.join().ok();
+            anyhow::bail!("{} hook '{}' timed out after {}s", stage, hook.command, timeout.as_secs());
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let captured = reader.join().unwrap_or_else(|_| Ok(String::new())).unwrap_or_default();
+
+    if !status.success() {
+        eprint!("{}", redact(&captured));
+        anyhow::bail!("{} hook '{}' failed with exit code {}", stage, hook.command,
+            status.code().map(|c| c.to_string()).unwrap_or_else(|| "unknown".to_string()));
+    }
+
+    match serde_json::from_str::<Value>(captured.trim()) {
+        Ok(Value::Object(derived)) => {
+            if let Some(vars) = variables.as_object_mut() {
+                vars.extend(derived);
+            }
+        }
+        _ => eprint!("{}", redact(&captured)),
+    }
+
+    Ok(HookResult {
+        stage: stage.to_string(),
+        command: hook.command.clone(),
+        exit_code: status.code(),
+        duration_ms: started.elapsed().as_millis(),
+        skipped: false,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use crate::test_support::plugin;
+    use crate::trust::ExecPolicy;
+
+    fn hook(command: &str, timeout_secs: Option<u64>) -> Hook {
+        Hook { command: command.to_string(), timeout_secs }
+    }
+
+    fn context(home: &Path) -> ForgeContext {
+        let mut ctx = ForgeContext::for_tests(home);
+        ctx.exec = ExecPolicy::Yes;
+        ctx
+    }
+
+    #[test]
+    fn json_output_is_merged_into_the_variables() {
+        let home = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let mut variables = json!({ "name": "shop" });
+        let result = run_hook(&context(home.path()), &hook(r#"echo "{\"slug\": \"$FORGE_VAR_NAME-app\"}""#, None),
+            "pre_generate", &plugin("web", json!({})), home.path(), out.path(), &mut variables).unwrap();
+
+        assert_eq!(result.exit_code, Some(0));
+        assert!(!result.skipped);
+        assert_eq!(variables, json!({ "name": "shop", "slug": "shop-app" }));
+    }
+
+    #[test]
+    fn failing_hooks_report_their_exit_code() {
+        let home = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let err = run_hook(&context(home.path()), &hook("exit 3", None),
+            "post_generate", &plugin("web", json!({})), home.path(), out.path(), &mut json!({})).unwrap_err();
+        assert_eq!(err.to_string(), "post_generate hook 'exit 3' failed with exit code 3");
+    }
+
+    #[test]
+    fn slow_hooks_are_killed_after_the_timeout() {
+        let home = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let started = Instant::now();
+        let err = run_hook(&context(home.path()), &hook("exec sleep 30", Some(1)),
+            "post_generate", &plugin("web", json!({})), home.path(), out.path(), &mut json!({})).unwrap_err();
+        assert!(err.to_string().contains("timed out after 1s"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn no_exec_skips_the_hook() {
+        let home = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let result = run_hook(&ctx, &hook("touch ran", None),
+            "post_generate", &plugin("web", json!({})), home.path(), out.path(), &mut json!({})).unwrap();
+        assert!(result.skipped);
+        assert!(!out.path().join("ran").exists());
+    }
+}
@@ -0,0 +1,247 @@
+// src/lifecycle.rs
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use anyhow::{Result, Context};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::context::ForgeContext;
+use crate::output::OutputFormat;
+use crate::plugin::{PluginMetadata, LIFECYCLE_STAGES};
+use crate::secrets;
+use crate::trust::ensure_trusted;
+
+/// Outcome of one command of a lifecycle stage.
+#[derive(Debug, Serialize)]
+pub struct ActionResult {
+    pub command: String,
+    /// `planned` (dry run), `ok`, `failed` or `skipped` (an earlier command
+    /// failed, or `--no-exec`).
+    pub status: &'static str,
+    pub exit_code: Option<i32>,
+}
+
+/// Result of `forge run <stage>`.
+#[derive(Debug, Serialize)]
+pub struct StageReport {
+    pub stage: String,
+    pub plugin: String,
+    pub project_dir: String,
+    pub dry_run: bool,
+    pub actions: Vec<ActionResult>,
+}
+
+impl StageReport {
+    /// Exit code of the first failed command, if any.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.actions.iter()
+            .find(|a| a.status == "failed")
+            .map(|a| a.exit_code.unwrap_or(1))
+    }
+}
+
+/// Environment variable name for a template variable: `FORGE_VAR_<KEY>`.
+pub fn variable_env_name(key: &str) -> String {
+    let key: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("FORGE_VAR_{}", key)
+}
+
+/// Environment passed to lifecycle commands: plugin and stage details plus
+/// every variable as `FORGE_VAR_<KEY>` (strings verbatim, other values as JSON).
+pub fn action_env(plugin: &PluginMetadata, stage: &str, project_dir: &Path, variables: &Value) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("FORGE_STAGE".to_string(), stage.to_string()),
+        ("FORGE_PLUGIN".to_string(), plugin.name.clone()),
+        ("FORGE_PLUGIN_VERSION".to_string(), plugin.version.clone()),
+        ("FORGE_PROJECT_DIR".to_string(), project_dir.to_string_lossy().into_owned()),
+    ];
+    if let Some(obj) = variables.as_object() {
+        for (key, value) in obj {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            env.push((variable_env_name(key), value));
+        }
+    }
+    env
+}
+
+/// Build a shell invocation for a declared command string.
+pub fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+/// Run the commands a plugin declares for `stage` in `project_dir`, stopping
+/// at the first failure. Output is streamed as it is produced; in JSON/YAML
+/// mode it goes to stderr so stdout stays machine-readable.
+///
+/// The plugin's `secrets` are looked up through `secret_refs` (the manifest's
+/// `secrets`) and added to the commands' environment.
+pub fn run_stage(
+    ctx: &ForgeContext,
+    plugin: &PluginMetadata,
+    stage: &str,
+    project_dir: &Path,
+    variables: &Value,
+    secret_refs: &BTreeMap<String, String>,
+    dry_run: bool,
+) -> Result<StageReport> {
+    let output = ctx.output;
+    if !LIFECYCLE_STAGES.contains(&stage) {
+        anyhow::bail!("Unknown lifecycle stage '{}'. Expected one of: {}", stage, LIFECYCLE_STAGES.join(", "));
+    }
+    let commands = plugin.actions.as_ref()
+        .and_then(|actions| actions.get(stage))
+        .with_context(|| format!("Plugin {} declares no actions for stage '{}'", plugin.name, stage))?;
+
+    let mut env = action_env(plugin, stage, project_dir, variables);
+    let has_secrets = !dry_run && !plugin.secrets.is_empty();
+    if has_secrets {
+        let resolved = secrets::resolve_all(ctx, &plugin.secrets, secret_refs)
+            .with_context(|| format!("Plugin {} needs secrets for its actions", plugin.name))?;
+        env.extend(resolved);
+    }
+    let mut report = StageReport {
+        stage: stage.to_string(),
+        plugin: plugin.name.clone(),
+        project_dir: project_dir.to_string_lossy().into_owned(),
+        dry_run,
+        actions: Vec::new(),
+    };
+
+    for command in commands {
+        if dry_run {
+            output.note(format!("would run: {}", command));
+            report.actions.push(ActionResult { command: command.clone(), status: "planned", exit_code: None });
+            continue;
+        }
+        if report.exit_code().is_some() || !ensure_trusted(ctx, plugin, &format!("action:{}", stage), command, &[project_dir])? {
+            report.actions.push(ActionResult { command: command.clone(), status: "skipped", exit_code: None });
+            continue;
+        }
+
+        output.note(format!("▶ {}", command));
+        let mut cmd = shell_command(command);
+        cmd.current_dir(project_dir).envs(env.iter().cloned());
+        let status = if has_secrets {
+            run_redacted(cmd, output)
+        } else {
+            if !output.is_table() {
+                cmd.stdout(Stdio::from(std::io::stderr()));
+            }
+            cmd.status().map_err(Into::into)
+        }.with_context(|| format!("Failed to run '{}'", command))?;
+
+        report.actions.push(ActionResult {
+            command: command.clone(),
+            status: if status.success() { "ok" } else { "failed" },
+            exit_code: status.code(),
+        });
+    }
+
+    Ok(report)
+}
+
+/// Run `cmd` with its output passed through `secrets::redact` line by line,
+/// so a command echoing a secret does not leak it into the log.
+fn run_redacted(mut cmd: Command, output: OutputFormat) -> Result<ExitStatus> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().context("stdout not captured")?;
+    let stderr = child.stderr.take().context("stderr not captured")?;
+    let to_stdout = output.is_table();
+    let relay_out = thread::spawn(move || relay(stdout, to_stdout));
+    let relay_err = thread::spawn(move || relay(stderr, false));
+    let status = child.wait()?;
+    relay_out.join().ok();
+    relay_err.join().ok();
+    Ok(status)
+}
+
+fn relay(stream: impl Read, to_stdout: bool) {
+    for line in BufReader::new(stream).lines().map_while(|l| l.ok()) {
+        if to_stdout {
+            println!("{}", secrets::redact(&line));
+        } else {
+            eprintln!("{}", secrets::redact(&line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use crate::test_support::plugin;
+    use crate::trust::ExecPolicy;
+
+    fn deployer(commands: &[&str]) -> PluginMetadata {
+        plugin("deploy", json!({ "actions": { "deploy": commands } }))
+    }
+
+    fn run(ctx: &ForgeContext, plugin: &PluginMetadata, stage: &str, project: &Path, dry_run: bool) -> Result<StageReport> {
+        run_stage(ctx, plugin, stage, project, &json!({ "app-name": "shop" }), &BTreeMap::new(), dry_run)
+    }
+
+    fn statuses(report: &StageReport) -> Vec<&str> {
+        report.actions.iter().map(|a| a.status).collect()
+    }
+
+    #[test]
+    fn variable_env_names_are_upper_case_identifiers() {
+        assert_eq!(variable_env_name("app-name"), "FORGE_VAR_APP_NAME");
+        assert_eq!(variable_env_name("db.port"), "FORGE_VAR_DB_PORT");
+    }
+
+    #[test]
+    fn unknown_and_undeclared_stages_are_errors() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let err = run(&ctx, &deployer(&["true"]), "release", home.path(), true).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown lifecycle stage 'release'"), "{}", err);
+        let err = run(&ctx, &deployer(&["true"]), "build", home.path(), true).unwrap_err();
+        assert_eq!(err.to_string(), "Plugin deploy declares no actions for stage 'build'");
+    }
+
+    #[test]
+    fn dry_runs_and_no_exec_run_nothing() {
+        let home = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let plugin = deployer(&["touch ran"]);
+
+        assert_eq!(statuses(&run(&ctx, &plugin, "deploy", project.path(), true).unwrap()), ["planned"]);
+        assert_eq!(statuses(&run(&ctx, &plugin, "deploy", project.path(), false).unwrap()), ["skipped"]);
+        assert!(!project.path().join("ran").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stages_stop_at_the_first_failure() {
+        let home = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        let mut ctx = ForgeContext::for_tests(home.path());
+        ctx.exec = ExecPolicy::Yes;
+        let plugin = deployer(&[r#"printf %s "$FORGE_STAGE $FORGE_VAR_APP_NAME" > env"#, "exit 2", "touch after"]);
+
+        let report = run(&ctx, &plugin, "deploy", project.path(), false).unwrap();
+        assert_eq!(statuses(&report), ["ok", "failed", "skipped"]);
+        assert_eq!(report.exit_code(), Some(2));
+        assert_eq!(std::fs::read_to_string(project.path().join("env")).unwrap(), "deploy shop");
+        assert!(!project.path().join("after").exists());
+    }
+}
@@ -0,0 +1,285 @@
+// src/llm.rs
+use std::path::Path;
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::context::ForgeContext;
+use crate::llm_cache::{CachedProvider, LlmMode};
+use crate::secrets;
+
+pub const LLM_PROVIDER_ENV: &str = "FORGE_LLM_PROVIDER";
+pub const LLM_URL_ENV: &str = "FORGE_LLM_URL";
+pub const LLM_MODEL_ENV: &str = "FORGE_LLM_MODEL";
+/// API key for OpenAI-compatible endpoints, also the entry looked up in the
+/// secrets file; local servers usually need none.
+pub const LLM_API_KEY_ENV: &str = "FORGE_LLM_API_KEY";
+
+const REQUEST_TIMEOUT_SECS: u64 = 300;
+
+/// Wire protocol of an LLM endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// `POST <url>/chat/completions`: OpenAI, llama.cpp's server, vLLM, LM Studio, ...
+    Openai,
+    /// `POST <url>/api/chat` of an Ollama server.
+    Ollama,
+}
+
+impl ProviderKind {
+    fn default_url(self) -> &'static str {
+        match self {
+            ProviderKind::Openai => "https://api.openai.com/v1",
+            ProviderKind::Ollama => "http://localhost:11434",
+        }
+    }
+}
+
+/// The `llm` section of the configuration (formerly of `.forge/manifest.json`,
+/// which is still read). `FORGE_LLM_PROVIDER`, `FORGE_LLM_URL` and
+/// `FORGE_LLM_MODEL` override it. The API key is never stored here, only a
+/// reference to where it is kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlmConfig {
+    pub provider: Option<ProviderKind>,
+    /// Base URL; defaults to the provider's public or local endpoint.
+    pub url: Option<String>,
+    pub model: Option<String>,
+    /// Secret reference for the API key (`pass:openai/api-key`, `op://...`);
+    /// defaults to `FORGE_LLM_API_KEY` from the environment or the secrets file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+/// A chat model that turns a prompt into text.
+pub trait LlmProvider {
+    /// `openai` or `ollama`.
+    fn provider(&self) -> &'static str;
+
+    fn model(&self) -> &str;
+
+    /// `provider:model`, as reported for generated files.
+    fn id(&self) -> String {
+        format!("{}:{}", self.provider(), self.model())
+    }
+
+    /// Send one system and one user message and return the reply.
+    fn complete(&self, system: &str, prompt: &str) -> Result<String>;
+}
+
+/// The provider configured for the project, wrapped in the cache for
+/// `--llm=record|replay`; `None` when there is none or `--llm=off`.
+pub fn configured_provider(ctx: &ForgeContext, project_dir: &Path) -> Result<Option<Box<dyn LlmProvider>>> {
+    let mode = ctx.llm;
+    if mode == LlmMode::Off {
+        return Ok(None);
+    }
+    let settings = &ctx.config.llm;
+    let Some(provider) = settings.provider else {
+        if mode == LlmMode::Replay {
+            anyhow::bail!("--llm=replay needs the provider and model the answers were recorded with; set {} and {}", LLM_PROVIDER_ENV, LLM_MODEL_ENV);
+        }
+        return Ok(None);
+    };
+    let url = settings.url.clone().unwrap_or_else(|| provider.default_url().to_string());
+    let model = settings.model.clone()
+        .with_context(|| format!("No LLM model configured; set {} or `forge config set llm.model <model>`", LLM_MODEL_ENV))?;
+    let url = url.trim_end_matches('/').to_string();
+
+    let provider: Box<dyn LlmProvider> = match provider {
+        ProviderKind::Openai => {
+            // Replay never contacts the provider, so it needs no key
+            let api_key = match mode {
+                LlmMode::Replay => None,
+                _ => secrets::resolve_named(ctx, LLM_API_KEY_ENV, settings.api_key.as_deref())
+                    .context("Cannot get the LLM API key")?,
+            };
+            Box::new(OpenAiProvider { url, model, api_key })
+        }
+        ProviderKind::Ollama => Box::new(OllamaProvider { url, model }),
+    };
+    Ok(Some(match mode {
+        LlmMode::Record | LlmMode::Replay => Box::new(CachedProvider::new(provider, project_dir, mode)),
+        LlmMode::Live | LlmMode::Off => provider,
+    }))
+}
+
+/// An OpenAI-compatible chat completions endpoint.
+pub struct OpenAiProvider {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn provider(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete(&self, system: &str, prompt: &str) -> Result<String> {
+        let body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+        });
+        let reply = post_json(&format!("{}/chat/completions", self.url), self.api_key.as_deref(), &body)?;
+        reply.pointer("/choices/0/message/content")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .with_context(|| format!("LLM reply from {} has no choices[0].message.content", self.url))
+    }
+}
+
+/// An Ollama server.
+pub struct OllamaProvider {
+    url: String,
+    model: String,
+}
+
+impl LlmProvider for OllamaProvider {
+    fn provider(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete(&self, system: &str, prompt: &str) -> Result<String> {
+        let body = json!({
+            "model": self.model,
+            "stream": false,
+            "options": { "temperature": 0 },
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+        });
+        let reply = post_json(&format!("{}/api/chat", self.url), None, &body)?;
+        reply.pointer("/message/content")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .with_context(|| format!("LLM reply from {} has no message.content", self.url))
+    }
+}
+
+fn post_json(url: &str, api_key: Option<&str>, body: &Value) -> Result<Value> {
+    let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS)).build();
+    let mut request = agent.post(url);
+    if let Some(key) = api_key {
+        request = request.set("Authorization", &format!("Bearer {}", key));
+    }
+    match request.send_json(body) {
+        Ok(response) => response.into_json().with_context(|| format!("LLM provider at {} sent invalid JSON", url)),
+        Err(ureq::Error::Status(code, response)) => {
+            let text = response.into_string().unwrap_or_default();
+            let text: String = text.trim().chars().take(500).collect();
+            anyhow::bail!("LLM provider at {} answered {}: {}", url, code, text)
+        }
+        Err(err) => anyhow::bail!("Cannot reach LLM provider at {}: {}", url, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, JoinHandle};
+    use tiny_http::{Response, Server};
+
+    /// What the provider sent: path, `Authorization` header and JSON body.
+    type Received = (String, Option<String>, Value);
+
+    /// Answer one request with `status` and `reply` on a local port.
+    fn serve_once(status: u16, reply: &str) -> (String, JoinHandle<Received>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let reply = reply.to_string();
+        let handle = thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let auth = request.headers().iter()
+                .find(|h| h.field.equiv("Authorization"))
+                .map(|h| h.value.to_string());
+            let received = (request.url().to_string(), auth, serde_json::from_str(&body).unwrap());
+            request.respond(Response::from_string(reply).with_status_code(status)).unwrap();
+            received
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn openai_sends_chat_completion_request() {
+        let (url, server) = serve_once(200, r#"{"choices":[{"message":{"role":"assistant","content":"fn main() {}"}}]}"#);
+        let provider = OpenAiProvider { url, model: "gpt-test".to_string(), api_key: Some("sk-test".to_string()) };
+
+        assert_eq!(provider.complete("be terse", "write main").unwrap(), "fn main() {}");
+        assert_eq!(provider.id(), "openai:gpt-test");
+
+        let (path, auth, body) = server.join().unwrap();
+        assert_eq!(path, "/chat/completions");
+        assert_eq!(auth.as_deref(), Some("Bearer sk-test"));
+        assert_eq!(body["model"], "gpt-test");
+        assert_eq!(body["temperature"], 0);
+        assert_eq!(body["messages"], json!([
+            { "role": "system", "content": "be terse" },
+            { "role": "user", "content": "write main" },
+        ]));
+    }
+
+    #[test]
+    fn openai_without_key_sends_no_authorization() {
+        let (url, server) = serve_once(200, r#"{"choices":[{"message":{"content":"ok"}}]}"#);
+        let provider = OpenAiProvider { url, model: "local".to_string(), api_key: None };
+
+        provider.complete("", "hi").unwrap();
+        let (_, auth, _) = server.join().unwrap();
+        assert_eq!(auth, None);
+    }
+
+    #[test]
+    fn openai_reply_without_content_is_an_error() {
+        let (url, server) = serve_once(200, r#"{"choices":[]}"#);
+        let provider = OpenAiProvider { url, model: "gpt-test".to_string(), api_key: None };
+
+        let err = provider.complete("", "hi").unwrap_err();
+        assert!(err.to_string().contains("no choices[0].message.content"), "{}", err);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn ollama_sends_chat_request() {
+        let (url, server) = serve_once(200, r#"{"message":{"role":"assistant","content":"print('hi')"},"done":true}"#);
+        let provider = OllamaProvider { url, model: "llama3".to_string() };
+
+        assert_eq!(provider.complete("be terse", "say hi").unwrap(), "print('hi')");
+        assert_eq!(provider.id(), "ollama:llama3");
+
+        let (path, auth, body) = server.join().unwrap();
+        assert_eq!(path, "/api/chat");
+        assert_eq!(auth, None);
+        assert_eq!(body["model"], "llama3");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["temperature"], 0);
+        assert_eq!(body["messages"][1], json!({ "role": "user", "content": "say hi" }));
+    }
+
+    #[test]
+    fn error_status_is_reported_with_the_reply() {
+        let (url, server) = serve_once(404, "model 'llama9' not found");
+        let provider = OllamaProvider { url: url.clone(), model: "llama9".to_string() };
+
+        let err = provider.complete("", "hi").unwrap_err().to_string();
+        assert_eq!(err, format!("LLM provider at {}/api/chat answered 404: model 'llama9' not found", url));
+        server.join().unwrap();
+    }
+}
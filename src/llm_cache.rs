@@ -0,0 +1,166 @@
+// src/llm_cache.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::llm::LlmProvider;
+
+/// Cache directory, relative to the project directory.
+pub const LLM_CACHE_DIR: &str = ".forge/llm-cache";
+
+/// How AI-generated files get their content (`--llm`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LlmMode {
+    /// Ask the provider every time (the default).
+    #[default]
+    Live,
+    /// Ask the provider and store each answer in .forge/llm-cache.
+    Record,
+    /// Use only stored answers and fail when one is missing; no network.
+    Replay,
+    /// Skip AI-generated files.
+    Off,
+}
+
+/// A stored answer. The request is kept alongside it so cache changes can be reviewed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub provider: String,
+    pub model: String,
+    pub system: String,
+    pub prompt: String,
+    pub response: String,
+    pub recorded: String,
+}
+
+/// Key of a request: SHA-256 over provider, model and the full system and
+/// user messages, which carry the prompt, variables and context files.
+pub fn cache_key(provider: &str, model: &str, system: &str, prompt: &str) -> String {
+    let request = json!({ "provider": provider, "model": model, "system": system, "prompt": prompt });
+    hex::encode(Sha256::digest(request.to_string().as_bytes()))
+}
+
+/// Wraps a provider to record answers to, or replay them from, the project's cache.
+pub struct CachedProvider {
+    inner: Box<dyn LlmProvider>,
+    dir: PathBuf,
+    mode: LlmMode,
+}
+
+impl CachedProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, project_dir: &Path, mode: LlmMode) -> CachedProvider {
+        CachedProvider { inner, dir: project_dir.join(LLM_CACHE_DIR), mode }
+    }
+
+    fn entry_path(&self, system: &str, prompt: &str) -> PathBuf {
+        let key = cache_key(self.inner.provider(), self.inner.model(), system, prompt);
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+impl LlmProvider for CachedProvider {
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn complete(&self, system: &str, prompt: &str) -> Result<String> {
+        let path = self.entry_path(system, prompt);
+        match self.mode {
+            LlmMode::Replay => {
+                let content = fs::read_to_string(&path).with_context(|| format!(
+                    "--llm=replay: no recorded answer from {} for this prompt (expected {}). Run with --llm=record to record it.",
+                    self.id(), path.display()))?;
+                let entry: CacheEntry = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                Ok(entry.response)
+            }
+            LlmMode::Record => {
+                let response = self.inner.complete(system, prompt)?;
+                let entry = CacheEntry {
+                    provider: self.inner.provider().to_string(),
+                    model: self.inner.model().to_string(),
+                    system: system.to_string(),
+                    prompt: prompt.to_string(),
+                    response: response.clone(),
+                    recorded: Utc::now().to_rfc3339(),
+                };
+                fs::create_dir_all(&self.dir)?;
+                fs::write(&path, serde_json::to_string_pretty(&entry)?)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok(response)
+            }
+            LlmMode::Live | LlmMode::Off => self.inner.complete(system, prompt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Answers every prompt with `answer`, or fails when there is none.
+    struct Canned {
+        answer: Option<&'static str>,
+    }
+
+    impl LlmProvider for Canned {
+        fn provider(&self) -> &'static str {
+            "canned"
+        }
+
+        fn model(&self) -> &str {
+            "m1"
+        }
+
+        fn complete(&self, _system: &str, prompt: &str) -> Result<String> {
+            let answer = self.answer.context("the provider was asked")?;
+            Ok(format!("{} {}", answer, prompt))
+        }
+    }
+
+    fn cached(project: &Path, answer: Option<&'static str>, mode: LlmMode) -> CachedProvider {
+        CachedProvider::new(Box::new(Canned { answer }), project, mode)
+    }
+
+    #[test]
+    fn replay_answers_what_record_stored() {
+        let project = TempDir::new().unwrap();
+        assert_eq!(cached(project.path(), Some("hello"), LlmMode::Record).complete("sys", "world").unwrap(), "hello world");
+        assert_eq!(fs::read_dir(project.path().join(LLM_CACHE_DIR)).unwrap().count(), 1);
+
+        let replay = cached(project.path(), None, LlmMode::Replay);
+        assert_eq!(replay.complete("sys", "world").unwrap(), "hello world");
+    }
+
+    #[test]
+    fn replay_misses_fail_without_asking_the_provider() {
+        let project = TempDir::new().unwrap();
+        cached(project.path(), Some("hello"), LlmMode::Record).complete("sys", "world").unwrap();
+
+        let replay = cached(project.path(), None, LlmMode::Replay);
+        let err = replay.complete("other system", "world").unwrap_err().to_string();
+        assert!(err.starts_with("--llm=replay: no recorded answer from canned:m1 for this prompt"), "{}", err);
+        assert!(err.ends_with("Run with --llm=record to record it."), "{}", err);
+    }
+
+    #[test]
+    fn cache_keys_cover_the_whole_request() {
+        let key = cache_key("openai", "gpt", "sys", "prompt");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("openai", "gpt", "sys", "prompt"));
+        assert_ne!(key, cache_key("ollama", "gpt", "sys", "prompt"));
+        assert_ne!(key, cache_key("openai", "gpt-mini", "sys", "prompt"));
+        assert_ne!(key, cache_key("openai", "gpt", "sys", "prompt!"));
+        assert_ne!(key, cache_key("openai", "gpt", "sys prompt", ""));
+    }
+}
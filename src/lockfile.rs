@@ -0,0 +1,243 @@
+// src/lockfile.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+const LOCK_VERSION: u32 = 1;
+
+/// Pinned plugin resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPlugin {
+    pub name: String,
+    pub version: String,
+    pub sha256: Option<String>,
+}
+
+/// Pinned template resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedTemplate {
+    /// `<plugin>/<template>`, see `template_key`.
+    pub name: String,
+    pub source: String,
+    pub commit: Option<String>,
+    pub sha256: String,
+}
+
+/// Contents of `.forge/forge.lock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeLock {
+    pub version: u32,
+    pub plugins: Vec<LockedPlugin>,
+    pub templates: Vec<LockedTemplate>,
+}
+
+impl Default for ForgeLock {
+    fn default() -> Self {
+        ForgeLock { version: LOCK_VERSION, plugins: Vec::new(), templates: Vec::new() }
+    }
+}
+
+/// How a template is named in the lock, whether `forge init` cloned it or
+/// `forge generate` rendered it: the plugin, then the template's directory or
+/// repository name.
+pub fn template_key(plugin: &str, template: &str) -> String {
+    format!("{}/{}", plugin, template)
+}
+
+pub fn lock_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(".forge/forge.lock")
+}
+
+impl ForgeLock {
+    pub fn load(path: &Path) -> Result<Option<ForgeLock>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        let lock: ForgeLock = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if lock.version != LOCK_VERSION {
+            anyhow::bail!("Unsupported lockfile version {} in {}", lock.version, path.display());
+        }
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    pub fn template(&self, name: &str) -> Option<&LockedTemplate> {
+        self.templates.iter().find(|t| t.name == name)
+    }
+
+    /// Merge newly resolved entries into the lock, returning a description of each change.
+    pub fn update(&mut self, plugins: Vec<LockedPlugin>, templates: Vec<LockedTemplate>) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        for plugin in plugins {
+            match self.plugins.iter_mut().find(|p| p.name == plugin.name) {
+                Some(existing) if *existing == plugin => {}
+                Some(existing) => {
+                    changes.push(format!("plugin {} {} -> {}", plugin.name,
+                        describe(&existing.version, existing.sha256.as_deref()),
+                        describe(&plugin.version, plugin.sha256.as_deref())));
+                    *existing = plugin;
+                }
+                None => {
+                    changes.push(format!("plugin {} added at {}", plugin.name, plugin.version));
+                    self.plugins.push(plugin);
+                }
+            }
+        }
+
+        for template in templates {
+            match self.templates.iter_mut().find(|t| t.name == template.name) {
+                Some(existing) if *existing == template => {}
+                Some(existing) => {
+                    changes.push(format!("template {} {} -> {}", template.name,
+                        describe(existing.commit.as_deref().unwrap_or("-"), Some(&existing.sha256)),
+                        describe(template.commit.as_deref().unwrap_or("-"), Some(&template.sha256))));
+                    *existing = template;
+                }
+                None => {
+                    changes.push(format!("template {} added from {}", template.name, template.source));
+                    self.templates.push(template);
+                }
+            }
+        }
+
+        self.plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        self.templates.sort_by(|a, b| a.name.cmp(&b.name));
+        changes
+    }
+}
+
+fn describe(version: &str, sha256: Option<&str>) -> String {
+    match sha256 {
+        Some(sha) => format!("{} ({})", version, sha.chars().take(12).collect::<String>()),
+        None => version.to_string(),
+    }
+}
+
+/// Record a resolution in the project's lockfile.
+///
+/// With `locked`, the lockfile is never written and any difference is an error.
+pub fn apply_lock(project_dir: &Path, plugins: Vec<LockedPlugin>, templates: Vec<LockedTemplate>, locked: bool) -> Result<()> {
+    let path = lock_path(project_dir);
+    let existing = ForgeLock::load(&path)?;
+    if locked && existing.is_none() {
+        anyhow::bail!("--locked was passed but {} does not exist", path.display());
+    }
+
+    let mut lock = existing.unwrap_or_default();
+    let changes = lock.update(plugins, templates);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    if locked {
+        anyhow::bail!("{} is out of date and --locked was passed:\n  {}", path.display(), changes.join("\n  "));
+    }
+    lock.save(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn plugin(name: &str, version: &str, sha256: &str) -> LockedPlugin {
+        LockedPlugin { name: name.to_string(), version: version.to_string(), sha256: Some(sha256.to_string()) }
+    }
+
+    fn template(name: &str, commit: &str, sha256: &str) -> LockedTemplate {
+        LockedTemplate {
+            name: name.to_string(),
+            source: "plugin:rust/templates".to_string(),
+            commit: Some(commit.to_string()),
+            sha256: sha256.to_string(),
+        }
+    }
+
+    #[test]
+    fn update_adds_new_entries_sorted() {
+        let mut lock = ForgeLock::default();
+        let changes = lock.update(
+            vec![plugin("rust", "1.0.0", "aa"), plugin("node", "2.0.0", "bb")],
+            vec![template("rust/templates", "abc123", "cc")],
+        );
+
+        assert_eq!(changes, vec![
+            "plugin rust added at 1.0.0",
+            "plugin node added at 2.0.0",
+            "template rust/templates added from plugin:rust/templates",
+        ]);
+        let names: Vec<&str> = lock.plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["node", "rust"]);
+    }
+
+    #[test]
+    fn update_with_the_same_resolution_changes_nothing() {
+        let mut lock = ForgeLock::default();
+        lock.update(vec![plugin("rust", "1.0.0", "aa")], vec![template("rust/templates", "abc123", "cc")]);
+        let before = lock.clone();
+
+        let changes = lock.update(vec![plugin("rust", "1.0.0", "aa")], vec![template("rust/templates", "abc123", "cc")]);
+        assert!(changes.is_empty(), "{:?}", changes);
+        assert_eq!(lock, before);
+    }
+
+    #[test]
+    fn update_replaces_changed_entries() {
+        let mut lock = ForgeLock::default();
+        lock.update(vec![plugin("rust", "1.0.0", "aaaaaaaaaaaaaaaa")], vec![template("rust/templates", "abc123", "cc")]);
+
+        let changes = lock.update(
+            vec![plugin("rust", "1.1.0", "bbbbbbbbbbbbbbbb")],
+            vec![template("rust/templates", "def456", "dd")],
+        );
+        assert_eq!(changes, vec![
+            "plugin rust 1.0.0 (aaaaaaaaaaaa) -> 1.1.0 (bbbbbbbbbbbb)",
+            "template rust/templates abc123 (cc) -> def456 (dd)",
+        ]);
+        assert_eq!(lock.plugins, vec![plugin("rust", "1.1.0", "bbbbbbbbbbbbbbbb")]);
+        assert_eq!(lock.template("rust/templates").and_then(|t| t.commit.as_deref()), Some("def456"));
+    }
+
+    #[test]
+    fn describe_shortens_checksums_by_character() {
+        assert_eq!(describe("1.0.0", Some("0123456789abcdef")), "1.0.0 (0123456789ab)");
+        assert_eq!(describe("1.0.0", Some("ééééééééééééé")), "1.0.0 (éééééééééééé)");
+        assert_eq!(describe("1.0.0", Some("ab")), "1.0.0 (ab)");
+        assert_eq!(describe("1.0.0", None), "1.0.0");
+    }
+
+    #[test]
+    fn update_keeps_entries_it_is_not_given() {
+        let mut lock = ForgeLock::default();
+        lock.update(vec![plugin("node", "2.0.0", "bb")], Vec::new());
+
+        let changes = lock.update(vec![plugin("rust", "1.0.0", "aa")], Vec::new());
+        assert_eq!(changes, vec!["plugin rust added at 1.0.0"]);
+        assert_eq!(lock.plugins.len(), 2);
+    }
+
+    #[test]
+    fn apply_lock_refuses_changes_when_locked() {
+        let project = TempDir::new().unwrap();
+        let err = apply_lock(project.path(), vec![plugin("rust", "1.0.0", "aa")], Vec::new(), true).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+
+        apply_lock(project.path(), vec![plugin("rust", "1.0.0", "aa")], Vec::new(), false).unwrap();
+        apply_lock(project.path(), vec![plugin("rust", "1.0.0", "aa")], Vec::new(), true).unwrap();
+        let err = apply_lock(project.path(), vec![plugin("rust", "1.1.0", "aa")], Vec::new(), true).unwrap_err();
+        assert!(err.to_string().contains("plugin rust 1.0.0 (aa) -> 1.1.0 (aa)"), "{}", err);
+
+        let saved = ForgeLock::load(&lock_path(project.path())).unwrap().unwrap();
+        assert_eq!(saved.plugins, vec![plugin("rust", "1.0.0", "aa")]);
+    }
+}
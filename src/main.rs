@@ -1,27 +1,103 @@
 // src/main.rs
+mod output;
+mod paths;
+mod config;
+mod context;
 mod plugin;
+mod registry;
+mod detect;
+mod manifest;
 mod templates;
+mod verify;
+mod trust;
+mod sandbox;
 mod runtime_checker;
 mod renderer;
+mod command_renderer;
+mod container;
+mod render_agent;
+mod llm;
+mod llm_cache;
+mod secrets;
+mod ai_generated;
+mod prompts;
+mod wasm_renderer;
+mod lockfile;
+mod lifecycle;
+mod hooks;
+mod template_manifest;
 mod template_processor;
+#[cfg(test)]
+mod test_support;
 mod commands {
     pub mod init;
+    pub mod detect;
     pub mod plugin_install;
+    pub mod plugin_new;
     pub mod generate;
+    pub mod run;
+    pub mod trust;
+    pub mod check;
+    pub mod agent;
+    pub mod config;
 }
 
 use commands::{
-    init::run_forge_init,
-    plugin_install::{plugin_install, plugin_list, plugin_remove},
+    init::{run_forge_init, InitOptions},
+    detect::run_detect,
+    plugin_install::{plugin_info, plugin_install, plugin_list, plugin_remove},
+    plugin_new::{plugin_new, plugin_validate},
     generate::{GenerateSubcommand, handle_generate},
+    run::{run_lifecycle_stage, RunArgs},
+    trust::{trust_list, trust_revoke, trust_set},
+    check::run_check,
+    agent::agent_serve,
+    config::{config_get, config_list, config_set},
 };
 use clap::{Parser, Subcommand};
 use anyhow::Result;
+use context::ForgeContext;
+use output::OutputFormat;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "forge")]
 #[command(about = "AI-powered project scaffolding CLI", long_about = None)]
 struct Cli {
+    /// Keep plugins, approvals, keys, secrets, the global config and the cache
+    /// in this directory instead of the XDG directories (or set FORGE_HOME)
+    #[arg(long, global = true, value_name = "DIR")]
+    home: Option<PathBuf>,
+
+    /// Output format: human-readable table, or JSON/YAML for scripting
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// Install or run plugins and templates that fail checksum or signature verification
+    #[arg(long, global = true)]
+    allow_unverified: bool,
+
+    /// Fail instead of updating .forge/forge.lock when resolution would change it
+    #[arg(long, global = true)]
+    locked: bool,
+
+    /// Run plugin-declared commands without asking (approvals are not recorded)
+    #[arg(short, long, global = true, conflicts_with = "no_exec")]
+    yes: bool,
+
+    /// Never run plugin-declared renderers, actions or hooks
+    #[arg(long, global = true)]
+    no_exec: bool,
+
+    /// Run every renderer and hook in a sandbox (Linux, requires bubblewrap)
+    #[arg(long, global = true)]
+    sandbox: bool,
+
+    /// How AI-generated files are written: ask the model (live), also store its
+    /// answers in .forge/llm-cache (record), use only stored answers (replay), or skip them (off)
+    #[arg(long, global = true, value_enum, default_value_t = llm_cache::LlmMode::Live)]
+    llm: llm_cache::LlmMode,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -37,6 +113,10 @@ enum Commands {
 
         #[arg(short, long)]
         force: bool,
+
+        /// Also detect plugins in subdirectories and record them as components
+        #[arg(short, long)]
+        recursive: bool,
     },
     #[command(alias = "gen")]
     Generate {
@@ -47,6 +127,133 @@ enum Commands {
         #[command(subcommand)]
         action: PluginCommand,
     },
+    /// Run the plugin's commands for a lifecycle stage
+    Run {
+        /// generate, update, validate, test, deploy, notify, publish or build
+        stage: String,
+
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `update` actions
+    Update {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `validate` actions
+    Validate {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `test` actions
+    Test {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `build` actions
+    Build {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `deploy` actions
+    Deploy {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `publish` actions
+    Publish {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Run the plugin's `notify` actions
+    Notify {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Manage approved plugin commands in trust.json
+    Trust {
+        #[command(subcommand)]
+        action: TrustCommand,
+    },
+    /// Check that runtimes, packages, git and installed plugins are healthy
+    Check {
+        /// Check for this plugin instead of the project's
+        #[arg(short, long)]
+        plugin: Option<String>,
+    },
+    /// Run a render agent that renders templates for other machines
+    Agent {
+        #[command(subcommand)]
+        action: AgentCommand,
+    },
+    /// Show or change settings in the global config.toml and .forge/config.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Explain which plugin matches a project and why
+    Detect {
+        /// Project directory (defaults to the current directory)
+        path: Option<PathBuf>,
+
+        /// List the components detected in subdirectories instead
+        #[arg(short, long)]
+        recursive: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrustCommand {
+    /// List approved commands
+    List,
+    /// Forget approvals for a plugin
+    Revoke {
+        plugin: String,
+
+        /// Only revoke approvals for this version
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Set a plugin's trust level: `sandboxed` runs its renderers and hooks in a sandbox
+    Set {
+        plugin: String,
+
+        #[arg(value_enum)]
+        level: trust::TrustLevel,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print a setting's effective value
+    Get {
+        key: String,
+    },
+    /// Write a setting to the project's .forge/config.toml
+    Set {
+        key: String,
+        value: String,
+
+        /// Write to the global config.toml instead
+        #[arg(long)]
+        global: bool,
+    },
+    /// List every setting with its value and where it comes from
+    List,
+}
+
+#[derive(Subcommand)]
+enum AgentCommand {
+    /// Serve this machine's built-in engines over HTTP
+    Serve {
+        /// Address to listen on; other than loopback only with FORGE_AGENT_TOKEN set
+        #[arg(long, default_value = render_agent::DEFAULT_AGENT_LISTEN)]
+        listen: String,
+
+        /// Render without the sandbox (requests can run arbitrary Ruby or JavaScript)
+        #[arg(long)]
+        no_sandbox: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -55,33 +262,139 @@ enum PluginCommand {
         plugin: String,
     },
     List,
+    Info {
+        plugin: String,
+    },
     Remove {
         plugin: String,
+
+        /// Also remove installed plugins that depend on this one
+        #[arg(long)]
+        cascade: bool,
+    },
+    /// Scaffold a new plugin directory
+    New {
+        name: String,
+
+        /// Directory to create (defaults to ./<name>)
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+    },
+    /// Lint a plugin directory
+    Validate {
+        #[arg(default_value = ".")]
+        path: PathBuf,
     },
 }
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(err) = run() {
+        // Errors may quote a provider's reply or a command's output
+        eprintln!("Error: {}", secrets::redact(&format!("{:?}", err)));
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let cli = Cli::parse();
-    let index_path = dirs::home_dir().unwrap().join(".forge/plugin-index.json");
+    let paths = paths::ForgePaths::resolve(cli.home.clone())?;
+    let layers = match config::ConfigLayers::load(&paths, &std::env::current_dir()?) {
+        Ok(layers) => Some(layers),
+        // `forge config set` must still work to repair a broken setting
+        Err(err) if matches!(cli.command, Commands::Config { .. }) => {
+            eprintln!("⚠️  {:#}", err);
+            None
+        }
+        Err(err) => return Err(err),
+    };
+    let settings = match &layers {
+        Some(layers) => layers.merged()?,
+        None => config::ForgeConfig::default(),
+    };
+    // Flags win over the configuration
+    let exec = if cli.no_exec {
+        trust::ExecPolicy::NoExec
+    } else if cli.yes {
+        trust::ExecPolicy::Yes
+    } else {
+        settings.trust.exec
+    };
+    // Agent requests are templates from other machines, sandboxed by default
+    let agent_sandbox = matches!(cli.command, Commands::Agent { action: AgentCommand::Serve { no_sandbox: false, .. } });
+    let ctx = ForgeContext {
+        sandbox: cli.sandbox || settings.trust.sandbox || agent_sandbox,
+        paths,
+        config: settings,
+        output: cli.output,
+        allow_unverified: cli.allow_unverified,
+        locked: cli.locked,
+        exec,
+        llm: cli.llm,
+    };
+    let index_path = ctx.paths.plugin_index();
 
     match cli.command {
-        Commands::Init { plugin, template, force } => {
-            run_forge_init(plugin, template, force)?;
+        Commands::Init { plugin, template, force, recursive } => {
+            run_forge_init(&ctx, InitOptions { plugin, template, force, recursive })?;
         },
         Commands::Generate { action } => {
-            handle_generate(action)?;
+            handle_generate(&ctx, action)?;
+        },
+        Commands::Run { stage, args } => {
+            run_lifecycle_stage(&ctx, &stage, args)?;
+        },
+        Commands::Update { args } => run_lifecycle_stage(&ctx, "update", args)?,
+        Commands::Validate { args } => run_lifecycle_stage(&ctx, "validate", args)?,
+        Commands::Test { args } => run_lifecycle_stage(&ctx, "test", args)?,
+        Commands::Build { args } => run_lifecycle_stage(&ctx, "build", args)?,
+        Commands::Deploy { args } => run_lifecycle_stage(&ctx, "deploy", args)?,
+        Commands::Publish { args } => run_lifecycle_stage(&ctx, "publish", args)?,
+        Commands::Notify { args } => run_lifecycle_stage(&ctx, "notify", args)?,
+        Commands::Trust { action } => match action {
+            TrustCommand::List => trust_list(&ctx)?,
+            TrustCommand::Revoke { plugin, version } => trust_revoke(&ctx, &plugin, version.as_deref())?,
+            TrustCommand::Set { plugin, level } => trust_set(&ctx, &plugin, level)?,
+        },
+        Commands::Check { plugin } => run_check(&ctx, plugin)?,
+        Commands::Agent { action } => match action {
+            AgentCommand::Serve { listen, .. } => agent_serve(&ctx, &listen)?,
+        },
+        Commands::Config { action } => match action {
+            ConfigCommand::Get { key } => config_get(&ctx, &key, &loaded_layers(&ctx, layers)?)?,
+            ConfigCommand::Set { key, value, global } => config_set(&ctx, &key, &value, global)?,
+            ConfigCommand::List => config_list(&ctx, &loaded_layers(&ctx, layers)?)?,
+        },
+        Commands::Detect { path, recursive } => {
+            run_detect(&ctx, path, recursive)?;
         },
         Commands::Plugin { action } => match action {
             PluginCommand::Install { plugin } => {
-                plugin_install(&plugin, index_path)?;
+                plugin_install(&ctx, &plugin, index_path)?;
             },
             PluginCommand::List => {
-                plugin_list(index_path)?;
+                plugin_list(&ctx, index_path)?;
+            },
+            PluginCommand::Info { plugin } => {
+                plugin_info(&ctx, &plugin, index_path)?;
+            },
+            PluginCommand::Remove { plugin, cascade } => {
+                plugin_remove(&ctx, &plugin, index_path, cascade)?;
             },
-            PluginCommand::Remove { plugin } => {
-                plugin_remove(&plugin, index_path)?;
+            PluginCommand::New { name, dir } => {
+                plugin_new(&ctx, &name, dir)?;
+            },
+            PluginCommand::Validate { path } => {
+                plugin_validate(&ctx, &path)?;
             },
         },
     }
     Ok(())
 }
+
+/// The layers read at startup, or the error that kept them from loading.
+fn loaded_layers(ctx: &ForgeContext, layers: Option<config::ConfigLayers>) -> Result<config::ConfigLayers> {
+    match layers {
+        Some(layers) => Ok(layers),
+        None => config::ConfigLayers::load(&ctx.paths, &std::env::current_dir()?),
+    }
+}
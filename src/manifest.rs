@@ -0,0 +1,71 @@
+// src/manifest.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::llm::LlmConfig;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TemplateEntry {
+    pub name: String,
+    pub url: String,
+}
+
+/// A part of the project, rooted at `path`, that is scaffolded by `plugin`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub name: String,
+    /// Path relative to the project root; `.` for the root itself.
+    pub path: String,
+    pub plugin: String,
+}
+
+/// Contents of `.forge/manifest.json`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForgeManifest {
+    /// Plugin for the project root.
+    pub plugin: String,
+    pub templates: Vec<TemplateEntry>,
+    pub created: String,
+    /// Every component of a multi-plugin project, including the root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<Component>,
+    /// URL of a render agent for the templates' built-in engines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_agent: Option<String>,
+    /// LLM provider for the templates' AI-generated files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<LlmConfig>,
+    /// Secrets of the plugins' actions, mapped to a reference to where each is
+    /// kept (`pass:...`, `op://...`); never to the value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, String>,
+}
+
+pub fn manifest_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(".forge/manifest.json")
+}
+
+impl ForgeManifest {
+    pub fn load(project_dir: &Path) -> Result<Option<ForgeManifest>> {
+        let path = manifest_path(project_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let path = manifest_path(project_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
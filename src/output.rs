@@ -0,0 +1,75 @@
+// src/output.rs
+use std::fmt::{self, Display};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::secrets::redact;
+
+/// Output format selected with the global `--output` flag.
+///
+/// `table` is the human-readable default. `json` and `yaml` print a single
+/// document to stdout per command; progress messages go to stderr so the
+/// document can be piped straight into other tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+}
+
+impl OutputFormat {
+    pub fn is_table(self) -> bool {
+        self == OutputFormat::Table
+    }
+
+    /// Print a progress or status message without polluting machine-readable output.
+    pub fn note(self, message: impl Display) {
+        let message = redact(&message.to_string());
+        if self.is_table() {
+            println!("{}", message);
+        } else {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Emit a command result, using `table` to render it for humans.
+    pub fn emit<T: Serialize>(self, value: &T, table: impl FnOnce(&T, &mut String) -> fmt::Result) -> Result<()> {
+        print!("{}", self.render(value, table)?);
+        Ok(())
+    }
+
+    /// The redacted text `emit` prints for `value`.
+    fn render<T: Serialize>(self, value: &T, table: impl FnOnce(&T, &mut String) -> fmt::Result) -> Result<String> {
+        let text = match self {
+            OutputFormat::Table => {
+                let mut text = String::new();
+                table(value, &mut text)?;
+                text
+            }
+            OutputFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            OutputFormat::Yaml => serde_yaml::to_string(value)?,
+        };
+        Ok(redact(&text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+    use crate::secrets::remember;
+
+    #[test]
+    fn every_format_redacts_secrets() {
+        remember("hunter2-output-test");
+        let value = serde_json::json!({ "token": "hunter2-output-test" });
+        for format in [OutputFormat::Table, OutputFormat::Json, OutputFormat::Yaml] {
+            let text = format.render(&value, |v, out| writeln!(out, "token {}", v["token"].as_str().unwrap())).unwrap();
+            assert!(text.contains("[redacted]"), "{:?}: {}", format, text);
+            assert!(!text.contains("hunter2"), "{:?}: {}", format, text);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// src/paths.rs
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+
+/// Overrides every forge directory; `--home` wins over it.
+pub const FORGE_HOME_ENV: &str = "FORGE_HOME";
+
+/// Where forge keeps its own files, outside any project.
+///
+/// With `--home <dir>` or `FORGE_HOME`, everything lives under that one
+/// directory. Otherwise the XDG base directories are used: data (plugins,
+/// index, trust store, keys, secrets) in `$XDG_DATA_HOME/forge`, the global
+/// `config.toml` in `$XDG_CONFIG_HOME/forge` and downloads in
+/// `$XDG_CACHE_HOME/forge`. An existing `~/.forge` keeps serving as the data
+/// directory, and its `cache` as the cache, unless the XDG variable is set.
+#[derive(Debug, Clone)]
+pub struct ForgePaths {
+    pub data: PathBuf,
+    pub config: PathBuf,
+    pub cache: PathBuf,
+}
+
+impl ForgePaths {
+    /// The directories for `--home` (when given) and the environment.
+    pub fn resolve(home: Option<PathBuf>) -> Result<ForgePaths> {
+        ForgePaths::resolve_from(home, &env::current_dir()?, dirs::home_dir(), |name| env::var_os(name))
+    }
+
+    /// `resolve` with the working directory, the user's home directory and
+    /// the environment passed in.
+    fn resolve_from(
+        home: Option<PathBuf>,
+        cwd: &Path,
+        user_home: Option<PathBuf>,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<ForgePaths> {
+        let home = home.or_else(|| var(FORGE_HOME_ENV).filter(|v| !v.is_empty()).map(PathBuf::from));
+        if let Some(home) = home {
+            let home = cwd.join(home);
+            return Ok(ForgePaths { data: home.clone(), config: home.clone(), cache: home.join("cache") });
+        }
+
+        let user_home = user_home.context("Could not determine home directory; set FORGE_HOME or pass --home")?;
+        let xdg = |name: &str| var(name).map(PathBuf::from).filter(|p| p.is_absolute());
+        let legacy = user_home.join(".forge");
+        let legacy_in_use = legacy.is_dir();
+
+        let data = match xdg("XDG_DATA_HOME") {
+            Some(dir) => dir.join("forge"),
+            None if legacy_in_use => legacy.clone(),
+            None => user_home.join(".local/share/forge"),
+        };
+        let config = xdg("XDG_CONFIG_HOME").unwrap_or_else(|| user_home.join(".config")).join("forge");
+        let cache = match xdg("XDG_CACHE_HOME") {
+            Some(dir) => dir.join("forge"),
+            None if legacy_in_use => legacy.join("cache"),
+            None => user_home.join(".cache/forge"),
+        };
+        Ok(ForgePaths { data, config, cache })
+    }
+
+    pub fn plugins_dir(&self) -> PathBuf {
+        self.data.join("plugins")
+    }
+
+    pub fn plugin_index(&self) -> PathBuf {
+        self.data.join("plugin-index.json")
+    }
+
+    pub fn trust_store(&self) -> PathBuf {
+        self.data.join("trust.json")
+    }
+
+    pub fn trusted_keys_dir(&self) -> PathBuf {
+        self.data.join("trusted-keys")
+    }
+
+    pub fn secrets_file(&self) -> PathBuf {
+        self.data.join("secrets.json")
+    }
+
+    pub fn global_config(&self) -> PathBuf {
+        self.config.join("config.toml")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn resolve(home: Option<&str>, user_home: &Path, vars: &[(&str, &str)]) -> ForgePaths {
+        let var = |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| OsString::from(value));
+        ForgePaths::resolve_from(home.map(PathBuf::from), Path::new("/work/project"), Some(user_home.to_path_buf()), var).unwrap()
+    }
+
+    #[test]
+    fn home_flag_wins_over_forge_home() {
+        let user = TempDir::new().unwrap();
+        let paths = resolve(Some("/opt/forge"), user.path(), &[(FORGE_HOME_ENV, "/srv/forge"), ("XDG_DATA_HOME", "/xdg/data")]);
+        assert_eq!(paths.data, Path::new("/opt/forge"));
+        assert_eq!(paths.global_config(), Path::new("/opt/forge/config.toml"));
+        assert_eq!(paths.cache, Path::new("/opt/forge/cache"));
+    }
+
+    #[test]
+    fn forge_home_keeps_everything_in_one_directory() {
+        let user = TempDir::new().unwrap();
+        let paths = resolve(None, user.path(), &[(FORGE_HOME_ENV, "/srv/forge"), ("XDG_CONFIG_HOME", "/xdg/config")]);
+        assert_eq!(paths.data, Path::new("/srv/forge"));
+        assert_eq!(paths.config, Path::new("/srv/forge"));
+        assert_eq!(paths.plugins_dir(), Path::new("/srv/forge/plugins"));
+    }
+
+    #[test]
+    fn relative_home_is_relative_to_the_working_directory() {
+        let user = TempDir::new().unwrap();
+        assert_eq!(resolve(Some(".forge-home"), user.path(), &[]).data, Path::new("/work/project/.forge-home"));
+        assert_eq!(resolve(None, user.path(), &[(FORGE_HOME_ENV, "home")]).data, Path::new("/work/project/home"));
+    }
+
+    #[test]
+    fn empty_forge_home_is_ignored() {
+        let user = TempDir::new().unwrap();
+        let paths = resolve(None, user.path(), &[(FORGE_HOME_ENV, "")]);
+        assert_eq!(paths.data, user.path().join(".local/share/forge"));
+    }
+
+    #[test]
+    fn xdg_defaults_without_overrides() {
+        let user = TempDir::new().unwrap();
+        let paths = resolve(None, user.path(), &[]);
+        assert_eq!(paths.data, user.path().join(".local/share/forge"));
+        assert_eq!(paths.config, user.path().join(".config/forge"));
+        assert_eq!(paths.cache, user.path().join(".cache/forge"));
+    }
+
+    #[test]
+    fn xdg_variables_are_used_when_absolute() {
+        let user = TempDir::new().unwrap();
+        let paths = resolve(None, user.path(), &[
+            ("XDG_DATA_HOME", "/xdg/data"),
+            ("XDG_CONFIG_HOME", "relative/config"),
+            ("XDG_CACHE_HOME", "/xdg/cache"),
+        ]);
+        assert_eq!(paths.data, Path::new("/xdg/data/forge"));
+        assert_eq!(paths.config, user.path().join(".config/forge"));
+        assert_eq!(paths.cache, Path::new("/xdg/cache/forge"));
+    }
+
+    #[test]
+    fn existing_dot_forge_stays_in_use_unless_xdg_is_set() {
+        let user = TempDir::new().unwrap();
+        std::fs::create_dir(user.path().join(".forge")).unwrap();
+
+        let paths = resolve(None, user.path(), &[]);
+        assert_eq!(paths.data, user.path().join(".forge"));
+        assert_eq!(paths.cache, user.path().join(".forge/cache"));
+        assert_eq!(paths.config, user.path().join(".config/forge"));
+
+        let paths = resolve(None, user.path(), &[("XDG_DATA_HOME", "/xdg/data")]);
+        assert_eq!(paths.data, Path::new("/xdg/data/forge"));
+        assert_eq!(paths.cache, user.path().join(".forge/cache"));
+    }
+
+    #[test]
+    fn missing_home_directory_is_an_error() {
+        let err = ForgePaths::resolve_from(None, Path::new("/"), None, |_| None).unwrap_err();
+        assert!(err.to_string().contains("set FORGE_HOME or pass --home"), "{}", err);
+        assert!(ForgePaths::resolve_from(None, Path::new("/"), None, |_| Some(OsString::from("/srv/forge"))).is_ok());
+    }
+}
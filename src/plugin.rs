@@ -1,9 +1,8 @@
 // src/plugin.rs
 use std::fs;
-use std::collections::HashMap;
-use anyhow::{Result, Context};
+use std::collections::{BTreeMap, HashMap};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::path::Path;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -11,70 +10,245 @@ pub struct PluginMetadata {
     pub name: String,
     pub version: String,
     pub description: Option<String>,
+    #[serde(default)]
     pub templates: String,
+    /// SHA-256 of the template tree (see `verify::digest_dir`) that `templates` must resolve to.
+    pub templates_sha256: Option<String>,
     pub detect: Option<PluginDetectRule>,
     pub actions: Option<HashMap<String, Vec<String>>>,
-    pub custom_renderer_command: Option<String>
+    /// Secrets the actions need, passed to them as environment variables of
+    /// the same name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    /// Deprecated `<runtime> <script>` form of `renderers`.
+    pub custom_renderer_command: Option<String>,
+    /// External renderer commands, each owning a set of extensions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renderers: Vec<CustomRenderer>,
+    /// In-process WASM renderer; preferred over `renderers`.
+    pub wasm_renderer: Option<WasmRendererSpec>,
+    /// Runtimes the plugin's templates need: a semver range (`"ruby": ">=3.1"`)
+    /// or `{ "version": ">=3.1", "packages": ["rails"] }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, RuntimeConstraint>,
+    /// Named prompt assets for AI-generated files.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompts: BTreeMap<String, PromptAsset>,
+    /// Image used to render with runtimes that are missing on the host.
+    pub container: Option<ContainerSpec>,
+    /// Other plugins this one needs, mapped to a semver range (e.g. `"^1.2"`).
+    pub depends_on: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct PluginDetectRule {
-    pub files: Vec<String>,
+pub use crate::detect::PluginDetectRule;
+pub use crate::container::ContainerSpec;
+pub use crate::prompts::PromptAsset;
+pub use crate::runtime_checker::RuntimeConstraint;
+pub use crate::wasm_renderer::WasmRendererSpec;
+pub use crate::command_renderer::CustomRenderer;
+
+impl PluginMetadata {
+    /// Declared `renderers`, followed by the legacy `custom_renderer_command`.
+    pub fn custom_renderers(&self) -> Vec<CustomRenderer> {
+        let mut renderers = self.renderers.clone();
+        renderers.extend(self.custom_renderer_command.as_deref().map(CustomRenderer::from_legacy_command));
+        renderers
+    }
 }
 
+/// Can `name` name a plugin? It becomes a directory under the plugins
+/// directory, so it must be a single plain path component.
+pub fn is_plugin_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Fail unless `name` is a valid plugin name, see `is_plugin_name`.
+pub fn check_plugin_name(name: &str) -> Result<()> {
+    if !is_plugin_name(name) {
+        anyhow::bail!("Invalid plugin name '{}': use letters, digits, '-', '_' and '.', starting with a letter or digit", name);
+    }
+    Ok(())
+}
+
+/// Lifecycle stages a plugin may declare `actions` for.
+pub const LIFECYCLE_STAGES: &[&str] = &[
+    "generate", "update", "validate", "test", "deploy", "notify", "publish", "build",
+];
+
 pub mod plugin_commands {
     use super::*;
+    use crate::context::ForgeContext;
+    use crate::detect::rank_plugins;
+    use crate::lockfile::LockedPlugin;
+    use crate::registry::{read_registry, registry_locations};
 
-    pub fn detect_plugin(path: &Path) -> Option<PluginMetadata> {
-        //let cwd = std::env::current_dir().context("Failed to get current directory").ok()?;
-        let current_path = env::current_dir().unwrap();
-        let index_path = dirs::home_dir().unwrap().join(".forge/plugin-index.json");
-        if !index_path.exists() {
-            println!("Plugin index not found");
-            println!("Using local plugin list");
-            println!("Searching for plugins in {}", current_path.display());
-            let index_path = current_path.join("plugin-list.json");
-            if !index_path.exists() {
-                return None;
-            }
+    /// Metadata of every plugin detection should consider: the installed
+    /// plugins, or the configured plugin lists when nothing is installed.
+    pub fn detection_candidates(ctx: &ForgeContext) -> Vec<PluginMetadata> {
+        let index = read_plugin_index(ctx).unwrap_or_default();
+        let mut names: Vec<&String> = index.iter()
+            .filter(|(_, entry)| entry.get("installed").and_then(|v| v.as_bool()) == Some(true))
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+
+        if !names.is_empty() {
+            return names.into_iter()
+                .filter_map(|name| load_plugin_metadata(ctx, name).ok())
+                .collect();
         }
-        let content = fs::read_to_string(index_path).ok()?;
-        let list: HashMap<String, serde_json::Value> = serde_json::from_str(&content).ok()?;
-        for (name, entry) in list.iter() {
-            if let Some(installed) = entry.get("installed") {
-                if installed.as_bool() != Some(true) {
-                    continue;
-                }
-            }
 
-            //let plugin_path: &str = entry.get("installed_path").unwrap_or_default();
-            if let Some(files) = entry.get("files") {
-                let found_all = files.as_array()?.iter().all(|f| {
-                    let file_name = f.as_str().unwrap_or_default();
-                    path.join(file_name).exists()
-                });
-
-                if found_all {
-                  if let Ok(plugin_metadata) = serde_json::from_value::<PluginMetadata>(entry.clone()) {
-                    return Some(plugin_metadata);
-                  }
+        eprintln!("No plugins installed; using the plugin list ({})", registry_locations(ctx));
+        let list = read_registry(ctx).unwrap_or_else(|err| {
+            eprintln!("⚠️  {:#}", err);
+            HashMap::new()
+        });
+
+        let mut plugins: Vec<PluginMetadata> = list.into_values()
+            .filter_map(|mut entry| {
+                // Registry entries declare detection as a top-level `files` list
+                if entry.get("detect").is_none()
+                    && let Some(files) = entry.get("files").cloned() {
+                    entry["detect"] = serde_json::json!({ "files": files });
                 }
-            }
+                serde_json::from_value::<PluginMetadata>(entry).ok()
+            })
+            .collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+
+    /// Pick the plugin whose detect rule best matches the project at `path`.
+    pub fn detect_plugin(ctx: &ForgeContext, path: &Path) -> Option<PluginMetadata> {
+        let plugins = detection_candidates(ctx);
+        let detection = rank_plugins(path, &plugins);
+        let selected = detection.selected?;
+
+        if !detection.ties.is_empty() {
+            eprintln!("⚠️  Plugins {} match as well as {}; using {}. Pass --plugin to choose.",
+                detection.ties.join(", "), selected, selected);
         }
+        plugins.into_iter().find(|p| p.name == selected)
+    }
+
+    fn read_plugin_index(ctx: &ForgeContext) -> Result<HashMap<String, serde_json::Value>> {
+        let index_path = ctx.paths.plugin_index();
+        match fs::read_to_string(&index_path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(_) => Ok(HashMap::new()),
+        }
+    }
 
-        return None
+    /// Exact version and checksum of an installed plugin, for `forge.lock`.
+    pub fn locked_plugin(ctx: &ForgeContext, plugin: &PluginMetadata) -> Result<LockedPlugin> {
+        let index = read_plugin_index(ctx)?;
+        let entry = index.get(&plugin.name);
+        let text = |key: &str| entry.and_then(|e| e.get(key)).and_then(|v| v.as_str()).map(String::from);
+        Ok(LockedPlugin {
+            name: plugin.name.clone(),
+            version: text("version").unwrap_or_else(|| plugin.version.clone()),
+            sha256: text("sha256"),
+        })
     }
 
+    /// Refuse to run an installed plugin that failed verification at install
+    /// time or whose files changed since, unless `--allow-unverified`.
+    pub fn verify_installed_plugin(ctx: &ForgeContext, plugin_name: &str) -> Result<()> {
+        let index = read_plugin_index(ctx)?;
+        let recorded = index.get(plugin_name).and_then(|e| e.get("sha256")).and_then(|v| v.as_str());
+        let verification = crate::verify::Verification {
+            sha256: recorded.unwrap_or_default().to_string(),
+            expected_sha256: recorded.map(String::from),
+            signed_by: None,
+            problem: installed_plugin_problem(ctx, plugin_name)?,
+        };
+        crate::verify::require_verified(&format!("plugin {}", plugin_name), &verification, ctx.allow_unverified)
+    }
+
+    /// Names of the plugins marked installed in the index.
+    pub fn installed_plugins(ctx: &ForgeContext) -> Result<Vec<String>> {
+        let mut names: Vec<String> = read_plugin_index(ctx)?.into_iter()
+            .filter(|(_, entry)| entry.get("installed").and_then(|v| v.as_bool()) == Some(true))
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        Ok(names)
+    }
 
-    pub fn load_plugin_metadata(plugin_name: &str) -> Result<PluginMetadata> {
-        let plugin_path = dirs::home_dir()
-            .context("Could not find home directory")?
-            .join(".forge/plugins")
+    /// Why an installed plugin cannot be trusted as installed, if it cannot.
+    pub fn installed_plugin_problem(ctx: &ForgeContext, plugin_name: &str) -> Result<Option<String>> {
+        let index = read_plugin_index(ctx)?;
+        let entry = index.get(plugin_name);
+        let recorded = entry.and_then(|e| e.get("sha256")).and_then(|v| v.as_str());
+        let verified = entry.and_then(|e| e.get("verified")).and_then(|v| v.as_bool()) == Some(true);
+        let path = entry.and_then(|e| e.get("path")).and_then(|v| v.as_str());
+
+        if let Some(path) = path
+            && !Path::new(path).is_dir() {
+            return Ok(Some(format!("plugin directory {} is missing", path)));
+        }
+        let problem = match (path, recorded) {
+            (Some(path), Some(recorded)) => {
+                let current = crate::verify::digest_dir(Path::new(path))?;
+                if current != recorded {
+                    Some(format!("installed files changed since install (expected {}, got {})", recorded, current))
+                } else if !verified {
+                    Some("plugin was installed with --allow-unverified".to_string())
+                } else {
+                    None
+                }
+            }
+            _ => Some("plugin has no recorded checksum; reinstall it".to_string()),
+        };
+        Ok(problem)
+    }
+
+    pub fn load_plugin_metadata(ctx: &ForgeContext, plugin_name: &str) -> Result<PluginMetadata> {
+        check_plugin_name(plugin_name)?;
+        let plugin_path = ctx.paths.plugins_dir()
             .join(plugin_name)
             .join("plugin.json");
 
-        let content = fs::read_to_string(plugin_path)?;
-        let metadata: PluginMetadata = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(&plugin_path)?;
+        let mut metadata: PluginMetadata = serde_json::from_str(&content)?;
+
+        // Templates bundled with the plugin are declared relative to its directory
+        if let Some(plugin_dir) = plugin_path.parent() {
+            let bundled = plugin_dir.join(&metadata.templates);
+            if !metadata.templates.is_empty() && Path::new(&metadata.templates).is_relative() && bundled.exists() {
+                metadata.templates = bundled.to_string_lossy().into_owned();
+            }
+            for renderer in &mut metadata.renderers {
+                let cwd = match renderer.cwd.as_deref() {
+                    Some(cwd) => plugin_dir.join(cwd),
+                    None => plugin_dir.to_path_buf(),
+                };
+                renderer.cwd = Some(cwd.to_string_lossy().into_owned());
+            }
+            if let Some(wasm) = &mut metadata.wasm_renderer
+                && Path::new(&wasm.module).is_relative() {
+                wasm.module = plugin_dir.join(&wasm.module).to_string_lossy().into_owned();
+            }
+            for prompt in metadata.prompts.values_mut() {
+                prompt.file = plugin_dir.join(&prompt.file).to_string_lossy().into_owned();
+            }
+        }
         Ok(metadata)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_names_are_single_path_components() {
+        for name in ["puppet", "my-plugin", "rails_7", "k8s.helm", "0ops"] {
+            assert!(is_plugin_name(name), "{}", name);
+        }
+        for name in ["", ".", "..", "../evil", "a/b", "a\\b", "/etc", ".hidden", "-flag", "name with space"] {
+            assert!(!is_plugin_name(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// src/prompts.rs
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::fs;
+use anyhow::{Result, Context};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tempfile::NamedTempFile;
+
+use crate::plugin::PluginMetadata;
+use crate::renderer::EngineRegistry;
+use crate::template_manifest::{TemplateManifest, TEMPLATE_MANIFEST};
+
+/// A named prompt in the `prompts` of `plugin.json` or `forge-template.json`,
+/// kept in a file so changes to it are reviewed like code.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PromptAsset {
+    /// Prompt file, relative to the plugin directory or template root. It is
+    /// rendered with the engine for its extension, like a template.
+    pub file: String,
+    /// Semver version, bumped when the prompt changes.
+    pub version: String,
+    /// System instructions sent along with the prompt.
+    pub system: Option<String>,
+    /// Variables the prompt is rendered with, mapped to the project variable
+    /// each is taken from (`a.b` reaches into objects). Without bindings the
+    /// prompt sees every project variable.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub output: PromptOutput,
+}
+
+/// Constraints on what the model may answer.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PromptOutput {
+    pub format: Option<PromptFormat>,
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptFormat {
+    Text,
+    Markdown,
+    Json,
+    Yaml,
+}
+
+impl PromptOutput {
+    /// Instructions telling the model about the constraints.
+    pub fn instructions(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        match self.format {
+            Some(PromptFormat::Text) => parts.push("Answer in plain text, without markup.".to_string()),
+            Some(PromptFormat::Markdown) => parts.push("Answer in Markdown.".to_string()),
+            Some(PromptFormat::Json) => parts.push("Answer with a single valid JSON document.".to_string()),
+            Some(PromptFormat::Yaml) => parts.push("Answer with a single valid YAML document.".to_string()),
+            None => {}
+        }
+        if let Some(max) = self.max_bytes {
+            parts.push(format!("Keep the answer under {} bytes.", max));
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Reject an answer that breaks the constraints.
+    pub fn check(&self, answer: &str) -> Result<()> {
+        if let Some(max) = self.max_bytes
+            && answer.len() > max {
+            anyhow::bail!("the answer is {} bytes, more than the {} allowed", answer.len(), max);
+        }
+        match self.format {
+            Some(PromptFormat::Json) => {
+                serde_json::from_str::<Value>(answer).context("the answer is not valid JSON")?;
+            }
+            Some(PromptFormat::Yaml) => {
+                serde_yaml::from_str::<serde_yaml::Value>(answer).context("the answer is not valid YAML")?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl PromptAsset {
+    /// Problems that make the declaration unusable; `base` is the directory `file` is relative to.
+    pub fn problems(&self, base: &Path) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Err(err) = Version::parse(&self.version) {
+            problems.push(format!("version '{}' is not valid semver: {}", self.version, err));
+        }
+        if self.file.trim().is_empty() {
+            problems.push("file must name the prompt file".to_string());
+        } else if !base.join(&self.file).is_file() {
+            problems.push(format!("file {} does not exist", self.file));
+        }
+        for (name, source) in &self.inputs {
+            if name.trim().is_empty() || source.trim().is_empty() {
+                problems.push("inputs must map variable names to project variables".to_string());
+            }
+        }
+        if self.output.max_bytes == Some(0) {
+            problems.push("output.max_bytes must be greater than 0".to_string());
+        }
+        problems
+    }
+}
+
+/// The prompt for one AI-generated file, ready to send.
+#[derive(Debug, Clone)]
+pub struct ResolvedPrompt {
+    /// `name@version` of the prompt asset; `None` for an inline prompt.
+    pub asset: Option<String>,
+    pub text: String,
+    pub system: Option<String>,
+    /// Variables the prompt was rendered with, also shown to the model.
+    pub variables: Value,
+    pub output: PromptOutput,
+}
+
+impl ResolvedPrompt {
+    pub fn inline(text: &str, variables: &Value) -> ResolvedPrompt {
+        ResolvedPrompt {
+            asset: None,
+            text: text.to_string(),
+            system: None,
+            variables: variables.clone(),
+            output: PromptOutput::default(),
+        }
+    }
+}
+
+/// Where prompt assets are looked up: the template's own, then the plugin's.
+pub struct PromptLibrary<'a> {
+    pub plugin: &'a PluginMetadata,
+    pub template: &'a TemplateManifest,
+    pub template_root: &'a Path,
+}
+
+impl PromptLibrary<'_> {
+    /// Find the asset for `name` or `name@<semver range>`, and the directory its file is relative to.
+    pub fn find(&self, reference: &str) -> Result<(String, &PromptAsset, PathBuf)> {
+        let (name, requirement) = match reference.split_once('@') {
+            Some((name, range)) => (name, Some(VersionReq::parse(range)
+                .with_context(|| format!("Invalid version range in prompt reference '{}'", reference))?)),
+            None => (reference, None),
+        };
+        let (asset, base, source) = match self.template.prompts.get(name) {
+            Some(asset) => (asset, self.template_root.to_path_buf(), TEMPLATE_MANIFEST.to_string()),
+            None => {
+                let asset = self.plugin.prompts.get(name)
+                    .with_context(|| format!("No prompt named '{}' in {} or plugin {}", name, TEMPLATE_MANIFEST, self.plugin.name))?;
+                (asset, PathBuf::new(), format!("plugin {}", self.plugin.name))
+            }
+        };
+        let version = Version::parse(&asset.version)
+            .with_context(|| format!("Prompt '{}' in {} has an invalid version '{}'", name, source, asset.version))?;
+        if let Some(requirement) = requirement
+            && !requirement.matches(&version) {
+            anyhow::bail!("Prompt '{}' in {} is version {}, but {} is required", name, source, version, requirement);
+        }
+        Ok((format!("{}@{}", name, version), asset, base))
+    }
+
+    /// Render the asset for `reference` with `engines`, binding its inputs from `variables`.
+    pub fn resolve(&self, reference: &str, engines: &EngineRegistry, variables: &Value) -> Result<ResolvedPrompt> {
+        let (id, asset, base) = self.find(reference)?;
+        let prompt_vars = bind_inputs(&asset.inputs, variables)
+            .with_context(|| format!("Cannot bind the inputs of prompt {}", id))?;
+
+        let prompt_file = base.join(&asset.file);
+        let rendered = NamedTempFile::new()?;
+        engines.render(&prompt_file, rendered.path(), &prompt_vars)
+            .with_context(|| format!("Failed to render prompt {} from {:?}", id, prompt_file))?;
+        let text = fs::read_to_string(rendered.path())?;
+
+        Ok(ResolvedPrompt {
+            asset: Some(id),
+            text,
+            system: asset.system.clone(),
+            variables: prompt_vars,
+            output: asset.output.clone(),
+        })
+    }
+}
+
+fn bind_inputs(inputs: &BTreeMap<String, String>, variables: &Value) -> Result<Value> {
+    if inputs.is_empty() {
+        return Ok(variables.clone());
+    }
+    let mut bound = Map::new();
+    for (name, source) in inputs {
+        let value = source.split('.')
+            .try_fold(variables, |value, key| value.get(key))
+            .with_context(|| format!("project variable '{}' (for input '{}') is not set", source, name))?;
+        bound.insert(name.clone(), value.clone());
+    }
+    Ok(Value::Object(bound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_support::plugin;
+
+    fn asset(file: &str, version: &str) -> Value {
+        json!({ "file": file, "version": version })
+    }
+
+    #[test]
+    fn find_prefers_the_template_and_checks_the_range() {
+        let web = plugin("web", json!({ "prompts": { "readme": asset("plugin.md", "1.0.0"), "docs": asset("docs.md", "2.1.0") } }));
+        let template: TemplateManifest = serde_json::from_value(json!({ "prompts": { "readme": asset("template.md", "1.4.0") } })).unwrap();
+        let library = PromptLibrary { plugin: &web, template: &template, template_root: Path::new("templates") };
+
+        let (id, found, base) = library.find("readme@^1.2").unwrap();
+        assert_eq!((id.as_str(), found.file.as_str(), base), ("readme@1.4.0", "template.md", PathBuf::from("templates")));
+        let (id, _, base) = library.find("docs").unwrap();
+        assert_eq!((id.as_str(), base), ("docs@2.1.0", PathBuf::new()));
+
+        let err = library.find("docs@^3").unwrap_err();
+        assert_eq!(err.to_string(), "Prompt 'docs' in plugin web is version 2.1.0, but ^3 is required");
+        let err = library.find("readme@~1.2").unwrap_err();
+        assert_eq!(err.to_string(), "Prompt 'readme' in forge-template.json is version 1.4.0, but ~1.2 is required");
+        let err = library.find("changelog").unwrap_err();
+        assert_eq!(err.to_string(), "No prompt named 'changelog' in forge-template.json or plugin web");
+        assert!(library.find("docs@not-a-range").is_err());
+    }
+
+    #[test]
+    fn bind_inputs_reaches_into_objects() {
+        let variables = json!({ "name": "shop", "db": { "engine": "postgres" } });
+        assert_eq!(bind_inputs(&BTreeMap::new(), &variables).unwrap(), variables);
+
+        let inputs = BTreeMap::from([("app".to_string(), "name".to_string()), ("engine".to_string(), "db.engine".to_string())]);
+        assert_eq!(bind_inputs(&inputs, &variables).unwrap(), json!({ "app": "shop", "engine": "postgres" }));
+
+        let inputs = BTreeMap::from([("port".to_string(), "db.port".to_string())]);
+        let err = bind_inputs(&inputs, &variables).unwrap_err();
+        assert_eq!(err.to_string(), "project variable 'db.port' (for input 'port') is not set");
+    }
+
+    #[test]
+    fn output_constraints_reject_long_or_malformed_answers() {
+        let output = PromptOutput { format: Some(PromptFormat::Json), max_bytes: Some(16) };
+        assert_eq!(output.instructions().unwrap(), "Answer with a single valid JSON document. Keep the answer under 16 bytes.");
+        output.check(r#"{"ok": true}"#).unwrap();
+        assert_eq!(output.check("not json").unwrap_err().to_string(), "the answer is not valid JSON");
+        assert_eq!(output.check(r#"{"ok": "far too long"}"#).unwrap_err().to_string(), "the answer is 22 bytes, more than the 16 allowed");
+        assert!(PromptOutput::default().instructions().is_none());
+    }
+}
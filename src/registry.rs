@@ -0,0 +1,83 @@
+// src/registry.rs
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::context::ForgeContext;
+
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// The available plugins from every plugin list in `registry.urls`, by name.
+/// A plugin listed more than once comes from the first list naming it.
+/// Missing local lists are skipped; remote lists are cached so they stay
+/// usable offline.
+pub fn read_registry(ctx: &ForgeContext) -> Result<HashMap<String, Value>> {
+    let mut registry = HashMap::new();
+    for location in &ctx.config.registry.urls {
+        let list = read_list(ctx, location)
+            .with_context(|| format!("Failed to read plugin list {}", location))?;
+        for (name, entry) in list {
+            registry.entry(name).or_insert(entry);
+        }
+    }
+    Ok(registry)
+}
+
+/// Configured plugin lists, for messages about an empty registry.
+pub fn registry_locations(ctx: &ForgeContext) -> String {
+    ctx.config.registry.urls.join(", ")
+}
+
+/// Is `location` a plugin list forge downloads (and caches)?
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Where the last downloaded copy of the plugin list at `url` is kept.
+pub fn cached_list(ctx: &ForgeContext, url: &str) -> PathBuf {
+    ctx.cache_dir().join("registry").join(format!("{}.json", hex::encode(Sha256::digest(url.as_bytes()))))
+}
+
+fn read_list(ctx: &ForgeContext, location: &str) -> Result<HashMap<String, Value>> {
+    if is_remote(location) {
+        return fetch_list(ctx, location);
+    }
+    let path = env::current_dir().unwrap_or_default().join(location);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content).context("Failed to parse plugin list content as JSON")
+}
+
+/// Download a plugin list, falling back to the cached copy when that fails.
+fn fetch_list(ctx: &ForgeContext, url: &str) -> Result<HashMap<String, Value>> {
+    let cached = cached_list(ctx, url);
+
+    let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(FETCH_TIMEOUT_SECS)).build();
+    let fetched = agent.get(url).call()
+        .map_err(anyhow::Error::from)
+        .and_then(|response| Ok(response.into_string()?));
+    let content = match fetched {
+        Ok(content) => {
+            serde_json::from_str::<HashMap<String, Value>>(&content)
+                .context("Failed to parse plugin list content as JSON")?;
+            if let Some(cache_dir) = cached.parent() {
+                fs::create_dir_all(cache_dir)?;
+            }
+            fs::write(&cached, &content)?;
+            content
+        }
+        Err(err) if cached.exists() => {
+            eprintln!("⚠️  Cannot fetch {} ({}); using the copy cached in {}", url, err, cached.display());
+            fs::read_to_string(&cached)?
+        }
+        Err(err) => return Err(err),
+    };
+    serde_json::from_str(&content).context("Failed to parse plugin list content as JSON")
+}
@@ -0,0 +1,450 @@
+// src/render_agent.rs
+use std::fs;
+use std::io::Read;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::TempDir;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::context::ForgeContext;
+use crate::manifest::ForgeManifest;
+use crate::renderer::{EngineRegistry, Requirement};
+use crate::sandbox;
+use crate::runtime_checker::{check_required_runtimes, DeclaredRuntime};
+use crate::wasm_renderer::DiagnosticLevel;
+
+/// Sets `agent.url`, which overrides the project's `render_agent`; set it to an
+/// empty string to render locally.
+pub const RENDER_AGENT_ENV: &str = "FORGE_RENDER_AGENT";
+/// Shared bearer token; the agent requires it when set, and clients send it to
+/// the globally configured `agent.url` only.
+pub const AGENT_TOKEN_ENV: &str = "FORGE_AGENT_TOKEN";
+pub const DEFAULT_AGENT_LISTEN: &str = "127.0.0.1:7878";
+
+const MAX_REQUEST_BYTES: u64 = 64 * 1024 * 1024;
+const REQUEST_TIMEOUT_SECS: u64 = 600;
+
+/// A template or rendered file, with a `/`-separated path relative to the template root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// `POST /v1/check`: can the agent render these extensions under these constraints?
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckRequest {
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub runtimes: Vec<DeclaredRuntime>,
+}
+
+/// `POST /v1/render`: render a template bundle with the given variables.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderRequest {
+    pub files: Vec<BundleFile>,
+    pub variables: Value,
+    #[serde(default)]
+    pub runtimes: Vec<DeclaredRuntime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentDiagnostic {
+    pub level: DiagnosticLevel,
+    /// Template the message is about, if any.
+    pub path: Option<String>,
+    pub message: String,
+}
+
+/// Reply to both endpoints. `files` is empty unless every file rendered.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RenderResponse {
+    #[serde(default)]
+    pub files: Vec<BundleFile>,
+    #[serde(default)]
+    pub diagnostics: Vec<AgentDiagnostic>,
+}
+
+impl RenderResponse {
+    fn failed(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.level == DiagnosticLevel::Error)
+    }
+}
+
+/// `/`-separated form of a path relative to the template root.
+pub fn bundle_path(relative: &Path) -> String {
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Templates split between the render agent and the local engines, with the
+/// runtime constraints each side checks.
+pub struct RenderPlan {
+    pub remote: Vec<PathBuf>,
+    pub local: Vec<PathBuf>,
+    pub remote_runtimes: Vec<DeclaredRuntime>,
+    pub local_runtimes: Vec<DeclaredRuntime>,
+}
+
+impl RenderPlan {
+    /// Send the files of built-in engines to `agent`, if there is one.
+    pub fn new(agent: Option<&RenderAgent>, engines: &EngineRegistry, paths: &[PathBuf], declared: Vec<DeclaredRuntime>) -> RenderPlan {
+        let (remote, local): (Vec<PathBuf>, Vec<PathBuf>) = paths.iter().cloned()
+            .partition(|path| agent.is_some() && engines.is_delegable(path));
+        let agent_runtimes: Vec<String> = remote.iter()
+            .flat_map(|path| engines.engine_for_path(path).requirements())
+            .filter_map(|requirement| match requirement {
+                Requirement::Runtime { name } => Some(name),
+                _ => None,
+            })
+            .collect();
+        let (remote_runtimes, local_runtimes) = declared.into_iter()
+            .partition(|d| agent_runtimes.contains(&d.runtime));
+        RenderPlan { remote, local, remote_runtimes, local_runtimes }
+    }
+
+    /// Extensions of the files the agent renders.
+    pub fn remote_extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = self.remote.iter()
+            .filter_map(|p| p.extension().map(|e| e.to_string_lossy().into_owned()))
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        extensions
+    }
+}
+
+/// Client for a render agent.
+pub struct RenderAgent {
+    pub url: String,
+    token: Option<String>,
+}
+
+impl RenderAgent {
+    /// The agent from `agent.url` (the global config or `$FORGE_RENDER_AGENT`)
+    /// or the project manifest's `render_agent`.
+    ///
+    /// The token only goes to `agent.url`: a cloned project names its own
+    /// agent, but must not collect the user's token.
+    pub fn configured(ctx: &ForgeContext, project_dir: &Path) -> Result<Option<RenderAgent>> {
+        if std::env::var(RENDER_AGENT_ENV).is_ok_and(|url| url.trim().is_empty()) {
+            return Ok(None);
+        }
+        let token = std::env::var(AGENT_TOKEN_ENV).ok().filter(|t| !t.is_empty());
+        let (url, token) = match ctx.config.agent.url.clone().filter(|url| !url.trim().is_empty()) {
+            Some(url) => (url, token),
+            None => match ForgeManifest::load(project_dir)?.and_then(|m| m.render_agent).filter(|url| !url.trim().is_empty()) {
+                Some(url) => {
+                    if token.is_some() {
+                        eprintln!("⚠️  Not sending {} to {}: it comes from the project manifest; set agent.url in the global config.toml to use the token",
+                            AGENT_TOKEN_ENV, url);
+                    }
+                    (url, None)
+                }
+                None => return Ok(None),
+            },
+        };
+        let url = url.trim_end_matches('/').to_string();
+        if token.is_some() {
+            check_token_url(&url)?;
+        }
+        Ok(Some(RenderAgent { url, token }))
+    }
+
+    /// Fail early when the agent cannot render `extensions` under `runtimes`.
+    pub fn check(&self, extensions: Vec<String>, runtimes: &[DeclaredRuntime]) -> Result<()> {
+        self.post("check", &CheckRequest { extensions, runtimes: runtimes.to_vec() })?;
+        Ok(())
+    }
+
+    /// Render a template bundle, reporting the agent's warnings on stderr.
+    pub fn render(&self, files: Vec<BundleFile>, variables: &Value, runtimes: &[DeclaredRuntime]) -> Result<Vec<BundleFile>> {
+        let request = RenderRequest { files, variables: variables.clone(), runtimes: runtimes.to_vec() };
+        let response = self.post("render", &request)?;
+        for diagnostic in &response.diagnostics {
+            eprintln!("  {} {}: {}", diagnostic.level.label(), diagnostic.path.as_deref().unwrap_or(&self.url), diagnostic.message);
+        }
+        Ok(response.files)
+    }
+
+    fn post<T: Serialize>(&self, endpoint: &str, body: &T) -> Result<RenderResponse> {
+        let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS)).build();
+        let mut request = agent.post(&format!("{}/v1/{}", self.url, endpoint));
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        let response = match request.send_json(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                let text = response.into_string().unwrap_or_default();
+                match serde_json::from_str::<RenderResponse>(&text) {
+                    Ok(reply) if reply.failed() => return Err(self.failure(&reply)),
+                    _ => anyhow::bail!("Render agent at {} answered {}: {}", self.url, code, text.trim()),
+                }
+            }
+            Err(err) => anyhow::bail!("Cannot reach render agent at {}: {}", self.url, err),
+        };
+        let reply: RenderResponse = response.into_json()
+            .with_context(|| format!("Render agent at {} sent an invalid reply", self.url))?;
+        if reply.failed() {
+            return Err(self.failure(&reply));
+        }
+        Ok(reply)
+    }
+
+    fn failure(&self, reply: &RenderResponse) -> anyhow::Error {
+        let mut message = format!("Render agent at {} cannot render the templates:", self.url);
+        for diagnostic in reply.diagnostics.iter().filter(|d| d.level == DiagnosticLevel::Error) {
+            let text = diagnostic.message.replace('\n', "\n    ");
+            match &diagnostic.path {
+                Some(path) => message.push_str(&format!("\n  - {}: {}", path, text)),
+                None => message.push_str(&format!("\n  - {}", text)),
+            }
+        }
+        anyhow::anyhow!(message)
+    }
+}
+
+/// `forge agent serve`: answer render requests with this machine's built-in
+/// engines until interrupted.
+///
+/// Templates are code (ERB and EJS run arbitrary Ruby and JavaScript), so
+/// without `FORGE_AGENT_TOKEN` the agent only listens on loopback, and the
+/// caller sandboxes renders unless told otherwise.
+pub fn serve(ctx: &ForgeContext, listen: &str) -> Result<()> {
+    let token = std::env::var(AGENT_TOKEN_ENV).ok().filter(|t| !t.is_empty());
+    let addresses: Vec<SocketAddr> = listen.to_socket_addrs()
+        .with_context(|| format!("Invalid listen address {}", listen))?
+        .collect();
+    if token.is_none() && addresses.iter().any(|addr| !addr.ip().is_loopback()) {
+        anyhow::bail!("Refusing to listen on {} without a token: set {} so only your clients can render", listen, AGENT_TOKEN_ENV);
+    }
+    if ctx.sandbox {
+        sandbox::ensure_available().context("The render agent sandboxes renders; pass --no-sandbox to run them unsandboxed")?;
+    }
+    let server = Server::http(listen)
+        .map_err(|err| anyhow::anyhow!("Cannot listen on {}: {}", listen, err))?;
+    let mut notes = Vec::new();
+    if token.is_none() {
+        notes.push("no token set; any local client may render");
+    }
+    if !ctx.sandbox {
+        notes.push("renders are not sandboxed");
+    }
+    eprintln!("Render agent listening on http://{}{}", listen,
+        if notes.is_empty() { String::new() } else { format!(" ({})", notes.join("; ")) });
+
+    for mut request in server.incoming_requests() {
+        let authorized = token.as_ref().is_none_or(|token| {
+            let expected = format!("Bearer {}", token);
+            request.headers().iter()
+                .any(|h| h.field.equiv("Authorization") && constant_time_eq(h.value.as_str().as_bytes(), expected.as_bytes()))
+        });
+        let mut body = Vec::new();
+        let read = request.as_reader().take(MAX_REQUEST_BYTES + 1).read_to_end(&mut body);
+
+        let (status, reply) = match (request.method(), request.url()) {
+            _ if !authorized => (401, error_reply("missing or wrong bearer token")),
+            _ if read.is_err() => (400, error_reply("could not read the request body")),
+            _ if body.len() as u64 > MAX_REQUEST_BYTES => (413, error_reply("request is too large")),
+            (Method::Post, "/v1/check") => match serde_json::from_slice(&body) {
+                Ok(check) => handle_check(ctx, check),
+                Err(err) => (400, error_reply(&format!("invalid check request: {}", err))),
+            },
+            (Method::Post, "/v1/render") => match serde_json::from_slice(&body) {
+                Ok(render) => handle_render(ctx, render),
+                Err(err) => (400, error_reply(&format!("invalid render request: {}", err))),
+            },
+            _ => (404, error_reply("unknown endpoint; use POST /v1/check or /v1/render")),
+        };
+        eprintln!("{} {} -> {}", request.method(), request.url(), status);
+
+        let json = serde_json::to_string(&reply)?;
+        let response = Response::from_string(json)
+            .with_status_code(status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").expect("valid header"));
+        if let Err(err) = request.respond(response) {
+            eprintln!("Failed to answer request: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Refuse to send the token in the clear to anything but loopback.
+fn check_token_url(url: &str) -> Result<()> {
+    let parsed = url::Url::parse(url).with_context(|| format!("Invalid render agent URL {}", url))?;
+    let loopback = match parsed.host() {
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        "http" => anyhow::bail!("Refusing to send {} to {} over plain http: use https, or a loopback address", AGENT_TOKEN_ENV, url),
+        scheme => anyhow::bail!("Render agent URL {} must use http or https, not {}", url, scheme),
+    }
+}
+
+/// Compare without returning early, so response times do not reveal how much
+/// of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut diff = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        diff |= (a.get(i).copied().unwrap_or(0) ^ b.get(i).copied().unwrap_or(0)) as usize;
+    }
+    diff == 0
+}
+
+fn error_reply(message: &str) -> RenderResponse {
+    RenderResponse {
+        files: Vec::new(),
+        diagnostics: vec![AgentDiagnostic { level: DiagnosticLevel::Error, path: None, message: message.to_string() }],
+    }
+}
+
+fn reply(files: Vec<BundleFile>, diagnostics: Vec<AgentDiagnostic>) -> (u16, RenderResponse) {
+    let mut response = RenderResponse { files, diagnostics };
+    if response.failed() {
+        response.files.clear();
+        return (422, response);
+    }
+    (200, response)
+}
+
+fn handle_check(ctx: &ForgeContext, request: CheckRequest) -> (u16, RenderResponse) {
+    let engines = EngineRegistry::for_plugin(ctx, None);
+    let paths: Vec<PathBuf> = request.extensions.iter().map(|ext| PathBuf::from(format!("template.{}", ext))).collect();
+    match check_required_runtimes(&engines, &paths, &request.runtimes) {
+        Ok(()) => reply(Vec::new(), Vec::new()),
+        Err(err) => reply(Vec::new(), vec![AgentDiagnostic { level: DiagnosticLevel::Error, path: None, message: format!("{:#}", err) }]),
+    }
+}
+
+fn handle_render(ctx: &ForgeContext, request: RenderRequest) -> (u16, RenderResponse) {
+    match render_bundle(ctx, request) {
+        Ok((files, diagnostics)) => reply(files, diagnostics),
+        Err(err) => (500, error_reply(&format!("{:#}", err))),
+    }
+}
+
+/// Render the bundle in a scratch directory with the built-in engines.
+fn render_bundle(ctx: &ForgeContext, request: RenderRequest) -> Result<(Vec<BundleFile>, Vec<AgentDiagnostic>)> {
+    let workspace = TempDir::new()?;
+    let template_root = workspace.path().join("templates");
+    let output_root = workspace.path().join("output");
+    let mut diagnostics = Vec::new();
+    let mut error = |path: &str, message: String| diagnostics.push(AgentDiagnostic {
+        level: DiagnosticLevel::Error,
+        path: Some(path.to_string()),
+        message,
+    });
+
+    let mut inputs = Vec::new();
+    for file in &request.files {
+        let relative = Path::new(&file.path);
+        if file.path.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            error(&file.path, "path must be relative and stay inside the bundle".to_string());
+            continue;
+        }
+        let input = template_root.join(relative);
+        if let Some(parent) = input.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&input, &file.content)?;
+        inputs.push((file.path.clone(), input));
+    }
+
+    let engines = EngineRegistry::for_plugin(ctx, None);
+    let paths: Vec<PathBuf> = inputs.iter().map(|(_, input)| input.clone()).collect();
+    if let Err(err) = check_required_runtimes(&engines, &paths, &request.runtimes) {
+        return Ok((Vec::new(), vec![AgentDiagnostic { level: DiagnosticLevel::Error, path: None, message: format!("{:#}", err) }]));
+    }
+
+    let mut files = Vec::new();
+    for (path, input) in inputs {
+        let output = output_root.join(&path);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match engines.render(&input, &output, &request.variables).and_then(|_| Ok(fs::read_to_string(&output)?)) {
+            Ok(content) => files.push(BundleFile { path, content }),
+            Err(err) => error(&path, format!("{:#}", err)),
+        }
+    }
+    Ok((files, diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(paths: &[&str]) -> RenderRequest {
+        RenderRequest {
+            files: paths.iter()
+                .map(|path| BundleFile { path: path.to_string(), content: "Hello {{name}}".to_string() })
+                .collect(),
+            variables: json!({ "name": "forge" }),
+            runtimes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn render_bundle_rejects_paths_outside_the_bundle() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let escapes = ["../escape.txt", "docs/../../escape.txt", "/etc/passwd", "./ok.txt", ""];
+        let mut paths = escapes.to_vec();
+        paths.push("docs/ok.txt");
+
+        let (files, diagnostics) = render_bundle(&ctx, request(&paths)).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "docs/ok.txt");
+        assert_eq!(files[0].content, "Hello \"forge\"");
+
+        let rejected: Vec<&str> = diagnostics.iter().filter_map(|d| d.path.as_deref()).collect();
+        assert_eq!(rejected, escapes);
+        assert!(diagnostics.iter().all(|d| d.level == DiagnosticLevel::Error
+            && d.message == "path must be relative and stay inside the bundle"));
+    }
+
+    #[test]
+    fn rejected_path_fails_the_whole_render() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+
+        let (status, response) = handle_render(&ctx, request(&["ok.txt", "../escape.txt"]));
+        assert_eq!(status, 422);
+        assert!(response.files.is_empty());
+
+        let (status, response) = handle_render(&ctx, request(&["ok.txt"]));
+        assert_eq!(status, 200);
+        assert_eq!(response.files.len(), 1);
+    }
+
+    #[test]
+    fn token_only_goes_over_https_or_to_loopback() {
+        for url in ["https://render.internal:7878", "http://127.0.0.1:7878", "http://localhost:7878", "http://[::1]:7878"] {
+            assert!(check_token_url(url).is_ok(), "{}", url);
+        }
+        for url in ["http://render.internal:7878", "http://10.0.0.5:7878", "ftp://localhost", "render.internal:7878"] {
+            assert!(check_token_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn compares_tokens_of_any_length() {
+        assert!(constant_time_eq(b"Bearer s3cret", b"Bearer s3cret"));
+        assert!(!constant_time_eq(b"Bearer s3cret", b"Bearer s3creT"));
+        assert!(!constant_time_eq(b"Bearer s3cret", b"Bearer s3cret2"));
+        assert!(!constant_time_eq(b"", b"Bearer s3cret"));
+    }
+}
@@ -1,117 +1,323 @@
 // src/renderer.rs
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use anyhow::{Result, Context};
-use tempfile::NamedTempFile;
-use std::io::Write;
+use tempfile::TempDir;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use crate::command_renderer::CommandEngine;
+use crate::context::ForgeContext;
+use crate::container::{container_for, ContainerEngine};
 use crate::plugin::PluginMetadata;
+use crate::sandbox::{sandboxed_command, SandboxSpec};
+use crate::trust::ensure_trusted;
+use crate::wasm_renderer::WasmEngine;
 
-/// Render a template file using native or external VM-based engines.
-pub fn render_template_file(
-    input_path: &Path,
-    output_path: &Path,
-    variables: &Value,
-    plugin: Option<&PluginMetadata>,
-) -> Result<()> {
-    let ext = input_path.extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-
-    let script_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scripts");
-
-    match ext {
-        "erb" => render_with_vm("ruby", script_dir.join("render_erb.rb"), input_path, output_path, variables),
-        "jinja" => render_with_vm("python3", script_dir.join("render_jinja.py"), input_path, output_path, variables),
-        "ejs" => render_with_vm("node", script_dir.join("render_ejs.js"), input_path, output_path, variables),
-        "hbs" => render_with_vm("node", script_dir.join("render_hbs.js"), input_path, output_path, variables),
-        _ => {
-              if let Some(plugin) = plugin {
-                  if let Some(runtime_command) = &plugin.custom_renderer_command {
-                      let mut parts = runtime_command.split_whitespace();
-                      let runtime = parts.next().context("Missing runtime in custom_renderer_command")?;
-                      let command_path = parts.next().context("Missing command path in custom_renderer_command")?;
-                      return render_with_plugin_command(runtime, command_path.to_string(), input_path, output_path, variables);
-                  }
-              }
-            render_fallback(input_path, output_path, variables)
+/// Something that must be present on the host before an engine can render.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Requirement {
+    /// An interpreter checked with `<name> --version`.
+    Runtime { name: String },
+    /// A plugin program; only checked for existence, since it may not be approved yet.
+    Program { name: String, cwd: Option<PathBuf> },
+    /// A library loaded by a runtime (a gem, pip package or npm module).
+    Package { runtime: String, name: String },
+}
+
+/// A render script of a built-in engine, compiled into forge and written to a
+/// scratch directory for each render.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderScript {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+impl RenderScript {
+    /// Write the script into `dir`, returning its path.
+    pub fn write_to(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join(self.name);
+        fs::write(&path, self.source).with_context(|| format!("Failed to write render script {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+const BUILTIN_SCRIPTS: [(&str, &str, &str, RenderScript); 4] = [
+    ("erb", "ruby", "erb", RenderScript { name: "render_erb.rb", source: include_str!("scripts/render_erb.rb") }),
+    ("jinja", "python3", "jinja2", RenderScript { name: "render_jinja.py", source: include_str!("scripts/render_jinja.py") }),
+    ("ejs", "node", "ejs", RenderScript { name: "render_ejs.js", source: include_str!("scripts/render_ejs.js") }),
+    ("hbs", "node", "handlebars", RenderScript { name: "render_hbs.js", source: include_str!("scripts/render_hbs.js") }),
+];
+
+/// What an engine does when it renders.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Capabilities {
+    /// Renders inside the forge process rather than spawning a program.
+    pub in_process: bool,
+    /// Runs code shipped by a plugin, which the user has to approve.
+    pub runs_plugin_code: bool,
+    /// Honors the plugin's `sandboxed` trust level.
+    pub sandboxable: bool,
+}
+
+/// A template engine.
+pub trait Renderer {
+    /// Short name shown to users, e.g. `erb` or `wasm:renderer.wasm`.
+    fn name(&self) -> String;
+
+    /// Extensions the engine owns; empty for a catch-all engine.
+    fn extensions(&self) -> Vec<String>;
+
+    fn requirements(&self) -> Vec<Requirement> {
+        Vec::new()
+    }
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// The command shown when asking the user to approve the engine.
+    fn approval_command(&self) -> String {
+        self.name()
+    }
+
+    /// Where the approval command's relative paths point, so that approval
+    /// covers the scripts it runs.
+    fn approval_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()>;
+}
+
+/// Whether an engine comes with forge or with the plugin; plugin engines win
+/// for the extensions they list unless `engines.prefer` is `builtin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineSource {
+    Builtin,
+    Plugin,
+}
+
+/// An engine as reported by `forge plugin info` and `forge check`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineInfo {
+    pub name: String,
+    pub source: EngineSource,
+    pub extensions: Vec<String>,
+    pub requirements: Vec<Requirement>,
+    pub capabilities: Capabilities,
+}
+
+struct Registered<'a> {
+    source: EngineSource,
+    engine: Box<dyn Renderer + 'a>,
+}
+
+/// Engines available for one plugin, and which one renders each extension.
+///
+/// Resolution order: a plugin engine listing the extension, a built-in engine
+/// listing it, a catch-all plugin engine, then the built-in fallback. With
+/// `engines.prefer = "builtin"` the first two swap. Within each group the
+/// first registered engine wins, so WASM beats commands.
+/// Built-in engines whose runtime is missing on the host run in the plugin's
+/// container, if it declares one.
+pub struct EngineRegistry<'a> {
+    ctx: &'a ForgeContext,
+    plugin: Option<&'a PluginMetadata>,
+    engines: Vec<Registered<'a>>,
+}
+
+impl<'a> EngineRegistry<'a> {
+    /// The built-in engines plus every engine the plugin declares.
+    pub fn for_plugin(ctx: &'a ForgeContext, plugin: Option<&'a PluginMetadata>) -> EngineRegistry<'a> {
+        let mut registry = EngineRegistry { ctx, plugin, engines: Vec::new() };
+        registry.register_builtins();
+        if let Some(plugin) = plugin {
+            if let Some(wasm) = &plugin.wasm_renderer {
+                registry.register(EngineSource::Plugin, Box::new(WasmEngine::new(wasm)));
+            }
+            for renderer in plugin.custom_renderers() {
+                registry.register(EngineSource::Plugin, Box::new(CommandEngine::new(ctx, plugin, renderer)));
+            }
+        }
+        registry
+    }
+
+    fn register_builtins(&mut self) {
+        let (ctx, plugin) = (self.ctx, self.plugin);
+        for (extension, runtime, package, script) in BUILTIN_SCRIPTS {
+            // The plugin's image stands in for a runtime missing on the host
+            let engine: Box<dyn Renderer + 'a> = match container_for(plugin, runtime) {
+                Some(spec) => Box::new(ContainerEngine { extension, runtime, script, spec, program: None }),
+                None => Box::new(VmEngine { ctx, extension, runtime, package, script, plugin }),
+            };
+            self.register(EngineSource::Builtin, engine);
+        }
+        self.register(EngineSource::Builtin, Box::new(FallbackEngine));
+    }
+
+    pub fn register(&mut self, source: EngineSource, engine: Box<dyn Renderer + 'a>) {
+        self.engines.push(Registered { source, engine });
+    }
+
+    /// Does the plugin's container provide `runtime` in place of the host?
+    pub fn runs_in_container(&self, runtime: &str) -> bool {
+        container_for(self.plugin, runtime).is_some()
+    }
+
+    pub fn engines(&self) -> impl Iterator<Item = (EngineSource, &dyn Renderer)> {
+        self.engines.iter().map(|r| (r.source, r.engine.as_ref()))
+    }
+
+    pub fn describe(&self) -> Vec<EngineInfo> {
+        self.engines()
+            .map(|(source, engine)| EngineInfo {
+                name: engine.name(),
+                source,
+                extensions: engine.extensions(),
+                requirements: engine.requirements(),
+                capabilities: engine.capabilities(),
+            })
+            .collect()
+    }
+
+    fn registered_for(&self, ext: &str) -> Option<&Registered<'a>> {
+        let owns = |r: &&Registered| r.engine.extensions().iter().any(|e| e.trim_start_matches('.') == ext);
+        let catch_all = |r: &&Registered| r.engine.extensions().is_empty();
+        let from = |source| move |r: &&Registered| r.source == source;
+        let (first, second) = match self.ctx.config.engines.prefer {
+            EngineSource::Plugin => (EngineSource::Plugin, EngineSource::Builtin),
+            EngineSource::Builtin => (EngineSource::Builtin, EngineSource::Plugin),
+        };
+        self.engines.iter().filter(from(first)).find(owns)
+            .or_else(|| self.engines.iter().filter(from(second)).find(owns))
+            .or_else(|| self.engines.iter().filter(from(EngineSource::Plugin)).find(catch_all))
+            .or_else(|| self.engines.iter().filter(from(EngineSource::Builtin)).find(catch_all))
+    }
+
+    /// The engine that renders files with extension `ext` (`""` for none).
+    pub fn engine_for(&self, ext: &str) -> &dyn Renderer {
+        self.registered_for(ext).map(|r| r.engine.as_ref()).unwrap_or(&FallbackEngine)
+    }
+
+    pub fn engine_for_path(&self, path: &Path) -> &dyn Renderer {
+        self.engine_for(path_extension(path))
+    }
+
+    /// Can `path` be rendered by a render agent instead? True for built-in
+    /// engines that run an external runtime; plugin engines always run locally.
+    pub fn is_delegable(&self, path: &Path) -> bool {
+        self.registered_for(path_extension(path))
+            .is_some_and(|r| r.source == EngineSource::Builtin && !r.engine.capabilities().in_process)
+    }
+
+    /// Render one template with the engine its extension maps to, asking for
+    /// approval first when the engine runs plugin code.
+    pub fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        let engine = self.engine_for_path(input_path);
+        if engine.capabilities().runs_plugin_code
+            && let Some(plugin) = self.plugin
+            && !ensure_trusted(self.ctx, plugin, "renderer", &engine.approval_command(), engine.approval_dir().as_slice())? {
+            anyhow::bail!("Cannot render {:?}: it needs the plugin renderer, which --no-exec prevents", input_path);
         }
+        engine.render(input_path, output_path, variables)
     }
 }
 
 /// Fallback simple rendering using Rust string replacement
-fn render_fallback(
-    input_path: &Path,
-    output_path: &Path,
-    variables: &Value,
-) -> Result<()> {
-    let content = fs::read_to_string(input_path)?;
-    let mut rendered = content;
-
-    if let Some(obj) = variables.as_object() {
-        for (key, value) in obj.iter() {
-            let placeholder = format!("{{{{{}}}}}", key);
-            rendered = rendered.replace(&placeholder, &value.to_string());
-        }
+struct FallbackEngine;
+
+impl Renderer for FallbackEngine {
+    fn name(&self) -> String {
+        "builtin".to_string()
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { in_process: true, ..Capabilities::default() }
     }
 
-    fs::write(output_path, rendered)?;
-    Ok(())
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        let content = fs::read_to_string(input_path)?;
+        let mut rendered = content;
+
+        if let Some(obj) = variables.as_object() {
+            for (key, value) in obj.iter() {
+                let placeholder = format!("{{{{{}}}}}", key);
+                rendered = rendered.replace(&placeholder, &value.to_string());
+            }
+        }
+
+        fs::write(output_path, rendered)?;
+        Ok(())
+    }
 }
 
 /// Render with external VM/runtime using a rendering script and JSON temp file
-fn render_with_vm(
-    runtime: &str,
-    script_path: PathBuf,
-    input_path: &Path,
-    output_path: &Path,
-    variables: &Value,
-) -> Result<()> {
-    let mut temp_file = NamedTempFile::new()?;
-    let vars_json = serde_json::to_string(variables)?;
-    temp_file.write_all(vars_json.as_bytes())?;
-    temp_file.flush()?;
-
-    let status = Command::new(runtime)
-        .arg(&script_path)
-        .arg(input_path)
-        .arg(output_path)
-        .arg(temp_file.path())
-        .status()
-        .context(format!("Failed to run {} script: {:?}", runtime, script_path))?;
-
-    if !status.success() {
-        anyhow::bail!("Rendering failed using {} for: {:?}", runtime, input_path);
-    }
-
-    Ok(())
-}
-
-/// Fallback renderer using plugin-defined custom command
-fn render_with_plugin_command(
-    runtime: &str,
-    command_path: String,
-    input_path: &Path,
-    output_path: &Path,
-    variables: &Value,
-) -> Result<()> {
-    let mut temp_file = NamedTempFile::new()?;
-    let vars_json = serde_json::to_string(variables)?;
-    temp_file.write_all(vars_json.as_bytes())?;
-    temp_file.flush()?;
-
-    let status = Command::new(runtime)
-        .arg(command_path)
-        .arg(input_path)
-        .arg(output_path)
-        .arg(temp_file.path())
-        .status()
-        .context("Failed to run plugin-provided render command")?;
-
-    if !status.success() {
-        anyhow::bail!("Rendering via plugin command failed for: {:?}", input_path);
-    }
-
-    Ok(())
+struct VmEngine<'a> {
+    ctx: &'a ForgeContext,
+    extension: &'static str,
+    runtime: &'static str,
+    package: &'static str,
+    script: RenderScript,
+    plugin: Option<&'a PluginMetadata>,
+}
+
+impl Renderer for VmEngine<'_> {
+    fn name(&self) -> String {
+        self.extension.to_string()
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        vec![self.extension.to_string()]
+    }
+
+    fn requirements(&self) -> Vec<Requirement> {
+        vec![
+            Requirement::Runtime { name: self.runtime.to_string() },
+            Requirement::Package { runtime: self.runtime.to_string(), name: self.package.to_string() },
+        ]
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { sandboxable: true, ..Capabilities::default() }
+    }
+
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        let job_dir = TempDir::new()?;
+        let script = self.script.write_to(job_dir.path())?;
+        let vars_path = job_dir.path().join("variables.json");
+        fs::write(&vars_path, serde_json::to_string(variables)?)?;
+
+        let spec = renderer_sandbox(job_dir.path(), input_path, output_path)?;
+        let args = [path_arg(&script)?, path_arg(input_path)?, path_arg(output_path)?, path_arg(&vars_path)?];
+        let status = sandboxed_command(self.ctx, self.plugin, self.runtime, &args.each_ref().map(String::as_str), &spec)?
+            .status()
+            .context(format!("Failed to run {} script {}", self.runtime, self.script.name))?;
+
+        if !status.success() {
+            anyhow::bail!("Rendering failed using {} for: {:?}", self.runtime, input_path);
+        }
+
+        Ok(())
+    }
+}
+
+/// Sandbox layout for a renderer: read the template's directory and the job
+/// directory holding the script and variables; write only the output file.
+fn renderer_sandbox(job_dir: &Path, input_path: &Path, output_path: &Path) -> Result<SandboxSpec> {
+    // The output must exist to be bind-mounted writable
+    fs::write(output_path, "")?;
+    let read_only = vec![input_path.parent().unwrap_or(input_path).to_path_buf(), job_dir.to_path_buf()];
+    Ok(SandboxSpec { read_only, writable: vec![output_path.to_path_buf()], workdir: None })
+}
+
+fn path_extension(path: &Path) -> &str {
+    path.extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+fn path_arg(path: &Path) -> Result<String> {
+    Ok(std::path::absolute(path)?.to_string_lossy().into_owned())
 }
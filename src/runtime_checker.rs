@@ -1,54 +1,326 @@
 // src/runtime_checker.rs
-use std::collections::{HashSet};
+use std::collections::BTreeMap;
 use std::process::Command;
 use std::path::Path;
-use anyhow::{Result, Context};
+use std::sync::LazyLock;
+use anyhow::Result;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
 
 use crate::plugin::PluginMetadata;
+use crate::renderer::{EngineRegistry, Requirement};
+use crate::template_manifest::{TemplateManifest, TEMPLATE_MANIFEST};
 
-/// Built-in mappings of file extensions to runtimes
-pub fn builtin_runtime_for_extension(ext: &str) -> Option<&'static str> {
-    match ext {
-        "erb" => Some("ruby"),
-        "jinja" => Some("python3"),
-        "ejs" | "hbs" => Some("node"),
-        _ => None,
+/// What a plugin or template needs from one runtime. Declared either as a
+/// version range (`"ruby": ">=3.1"`) or in full
+/// (`"ruby": { "version": ">=3.1", "packages": ["rails"] }`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RuntimeConstraintSpec")]
+pub struct RuntimeConstraint {
+    /// Semver range the installed version must match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Gems, pip packages or npm modules the runtime must be able to load.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuntimeConstraintSpec {
+    Range(String),
+    Full {
+        version: Option<String>,
+        #[serde(default)]
+        packages: Vec<String>,
+    },
+}
+
+impl TryFrom<RuntimeConstraintSpec> for RuntimeConstraint {
+    type Error = String;
+
+    fn try_from(spec: RuntimeConstraintSpec) -> Result<RuntimeConstraint, String> {
+        match spec {
+            RuntimeConstraintSpec::Range(version) => Ok(RuntimeConstraint { version: Some(version), packages: Vec::new() }),
+            RuntimeConstraintSpec::Full { version, packages } => {
+                // Names are handed to the runtime, so only plain identifiers are accepted
+                if let Some(bad) = packages.iter().find(|p| !is_package_name(p)) {
+                    return Err(format!(
+                        "'{}' is not a package name; use letters, digits, '_', '-', '.', '/' and a leading '@'", bad));
+                }
+                Ok(RuntimeConstraint { version, packages })
+            }
+        }
     }
 }
 
-/// Check required runtimes based on extensions and plugin overrides
-pub fn check_required_runtimes<P: AsRef<Path>>(
-    template_paths: &[P],
-    plugin: Option<&PluginMetadata>,
-) -> Result<()> {
-    let mut required: HashSet<String> = HashSet::new();
-
-    for path in template_paths {
-        if let Some(ext) = path.as_ref().extension().and_then(|e| e.to_str()) {
-            // Check plugin overrides first
-            if let Some(plugin) = plugin {
-              if let Some(runtime) = plugin.custom_renderer_command {
-                  required.insert(runtime.to_string());
-                  continue;
-              }
+static PACKAGE_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^@?[A-Za-z0-9_][A-Za-z0-9_.\-]*(/[A-Za-z0-9_][A-Za-z0-9_.\-]*)*$").expect("valid regex")
+});
+
+static VERSION_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d+)(?:\.(\d+))?(?:\.(\d+))?").expect("valid regex")
+});
+
+/// Is `name` a gem, pip or npm package name (`rails`, `jinja2`, `@scope/pkg`,
+/// `active_support/all`)?
+pub fn is_package_name(name: &str) -> bool {
+    PACKAGE_NAME.is_match(name)
+}
+
+/// A runtime constraint together with where it was declared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeclaredRuntime {
+    /// `plugin <name>` or `forge-template.json`.
+    pub source: String,
+    pub runtime: String,
+    pub constraint: RuntimeConstraint,
+}
+
+/// Runtime constraints from the plugin and the template manifest.
+pub fn declared_runtimes(plugin: Option<&PluginMetadata>, template: Option<&TemplateManifest>) -> Vec<DeclaredRuntime> {
+    let mut declared = Vec::new();
+    let mut add = |source: String, runtimes: &BTreeMap<String, RuntimeConstraint>| {
+        for (runtime, constraint) in runtimes {
+            declared.push(DeclaredRuntime { source: source.clone(), runtime: runtime.clone(), constraint: constraint.clone() });
+        }
+    };
+    if let Some(plugin) = plugin {
+        add(format!("plugin {}", plugin.name), &plugin.runtimes);
+    }
+    if let Some(template) = template {
+        add(TEMPLATE_MANIFEST.to_string(), &template.runtimes);
+    }
+    declared
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The outcome of checking one requirement, as shown by `forge check`.
+#[derive(Debug, Serialize)]
+pub struct CheckItem {
+    /// `runtime`, `package`, `program`, `tool`, `agent`, `plugin` or `home`.
+    pub category: String,
+    pub name: String,
+    pub status: CheckStatus,
+    pub version: Option<String>,
+    /// Version ranges declared for a runtime.
+    pub required: Option<String>,
+    pub detail: String,
+    /// How to fix a failed check.
+    pub fix: Option<String>,
+}
+
+pub fn item(category: &str, name: &str, status: CheckStatus, detail: impl Into<String>) -> CheckItem {
+    CheckItem {
+        category: category.to_string(),
+        name: name.to_string(),
+        status,
+        version: None,
+        required: None,
+        detail: detail.into(),
+        fix: None,
+    }
+}
+
+/// Requirements of the engines that will render `template_paths` (of every
+/// engine when the templates are not known), plus the declared runtimes and
+/// packages not provided by the plugin's container.
+pub fn required_for<P: AsRef<Path>>(
+    registry: &EngineRegistry,
+    template_paths: Option<&[P]>,
+    declared: &[DeclaredRuntime],
+) -> Vec<Requirement> {
+    let mut required = Vec::new();
+    let mut add = |requirement: Requirement| {
+        if !required.contains(&requirement) {
+            required.push(requirement);
+        }
+    };
+    match template_paths {
+        Some(paths) => {
+            for path in paths {
+                registry.engine_for_path(path.as_ref()).requirements().into_iter().for_each(&mut add);
+            }
+        }
+        None => {
+            for engine in registry.describe() {
+                engine.requirements.into_iter().for_each(&mut add);
             }
-            // Fallback to builtin runtime
-            if let Some(runtime) = builtin_runtime_for_extension(ext) {
-                required.insert(runtime.to_string());
+        }
+    }
+    // The image is trusted to meet the constraints of runtimes it provides
+    for declaration in declared.iter().filter(|d| !registry.runs_in_container(&d.runtime)) {
+        add(Requirement::Runtime { name: declaration.runtime.clone() });
+        for package in &declaration.constraint.packages {
+            add(Requirement::Package { runtime: declaration.runtime.clone(), name: package.clone() });
+        }
+    }
+    required
+}
+
+/// Check one requirement against the host.
+pub fn assess(requirement: &Requirement, declared: &[DeclaredRuntime]) -> CheckItem {
+    match requirement {
+        Requirement::Runtime { name } => assess_runtime(name, declared),
+        Requirement::Package { runtime, name } if !program_exists(runtime, None) => {
+            item("package", name, CheckStatus::Warning, format!("not checked: {} is not installed", runtime))
+        }
+        Requirement::Package { runtime, name } => match package_available(runtime, name) {
+            Some(true) => item("package", name, CheckStatus::Ok, format!("loads in {}", runtime)),
+            Some(false) => CheckItem {
+                fix: Some(package_install_hint(runtime, name)),
+                ..item("package", name, CheckStatus::Error, format!("cannot be loaded by {}", runtime))
+            },
+            None => item("package", name, CheckStatus::Warning, format!("cannot check packages for {}", runtime)),
+        },
+        Requirement::Program { name, cwd } => {
+            if program_exists(name, cwd.as_deref()) {
+                item("program", name, CheckStatus::Ok, "found")
+            } else {
+                CheckItem {
+                    fix: Some(format!("install {} or fix the plugin's renderer argv", name)),
+                    ..item("program", name, CheckStatus::Error, "not found")
+                }
             }
         }
     }
+}
 
-    for runtime in required {
-        let status = Command::new(&runtime)
-            .arg("--version")
-            .output()
-            .with_context(|| format!("Failed to check runtime '{}'. Is it installed?", runtime))?;
+fn assess_runtime(name: &str, declared: &[DeclaredRuntime]) -> CheckItem {
+    let ranges: Vec<(&str, &str)> = declared.iter()
+        .filter(|d| d.runtime == name)
+        .filter_map(|d| d.constraint.version.as_deref().map(|v| (d.source.as_str(), v)))
+        .collect();
+    let required = (!ranges.is_empty())
+        .then(|| ranges.iter().map(|(_, range)| *range).collect::<Vec<_>>().join(", "));
 
-        if !status.status.success() {
-            anyhow::bail!("Required runtime '{}' is not available.", runtime);
+    let Some((line, version)) = runtime_version(name) else {
+        return CheckItem {
+            required,
+            fix: Some(format!("install {} and make sure it is on PATH", name)),
+            ..item("runtime", name, CheckStatus::Error, "not installed")
+        };
+    };
+    let mut check = CheckItem {
+        version: version.as_ref().map(|v| v.to_string()),
+        required,
+        ..item("runtime", name, CheckStatus::Ok, line)
+    };
+    for (source, range) in ranges {
+        match (VersionReq::parse(range), &version) {
+            (Ok(req), Some(version)) if !req.matches(version) => {
+                check.status = CheckStatus::Error;
+                check.detail = format!("{} is installed but {} needs {}", version, source, range);
+                check.fix = Some(format!("upgrade {} to a version matching {}", name, range));
+                break;
+            }
+            (Ok(_), Some(_)) => {}
+            (Ok(_), None) => {
+                check.status = CheckStatus::Error;
+                check.detail = format!("cannot read a version from '{}' to compare with {}", check.detail, range);
+                break;
+            }
+            (Err(err), _) => {
+                check.status = CheckStatus::Error;
+                check.detail = format!("{} declares an invalid range '{}': {}", source, range, err);
+                break;
+            }
         }
     }
+    check
+}
 
+/// Check that every engine needed for `template_paths`, and every declared
+/// runtime and package, is available, reporting all problems at once.
+pub fn check_required_runtimes<P: AsRef<Path>>(
+    registry: &EngineRegistry,
+    template_paths: &[P],
+    declared: &[DeclaredRuntime],
+) -> Result<()> {
+    let problems: Vec<String> = required_for(registry, Some(template_paths), declared).iter()
+        .map(|requirement| assess(requirement, declared))
+        .filter(|check| check.status == CheckStatus::Error)
+        .map(|check| match &check.fix {
+            Some(fix) => format!("  - {} {}: {} (fix: {})", check.category, check.name, check.detail, fix),
+            None => format!("  - {} {}: {}", check.category, check.name, check.detail),
+        })
+        .collect();
+    if !problems.is_empty() {
+        anyhow::bail!("The templates cannot be rendered on this machine:\n{}\nRun `forge check` for details.", problems.join("\n"));
+    }
     Ok(())
 }
+
+/// Version reported by `<runtime> --version`, or `None` when it cannot be run.
+///
+/// Understands `ruby 3.2.2p53`, `Python 3.11.7`, `v20.1.0` and `git version
+/// 2.39.2`; missing minor or patch numbers are taken as 0.
+pub fn runtime_version(runtime: &str) -> Option<(String, Option<Version>)> {
+    let output = Command::new(runtime).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).into_owned() + &String::from_utf8_lossy(&output.stderr);
+    let first_line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("").trim().to_string();
+    Some((first_line.clone(), parse_version(&first_line)))
+}
+
+/// First `major[.minor[.patch]]` number in `text`.
+pub fn parse_version(text: &str) -> Option<Version> {
+    let caps = VERSION_NUMBER.captures(text)?;
+    let part = |i| caps.get(i).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+    Some(Version::new(part(1), part(2), part(3)))
+}
+
+/// Arguments that make `runtime` load `package`, for the runtimes forge knows.
+/// The name is passed as an argument, never as part of the code.
+pub fn package_probe(runtime: &str, package: &str) -> Option<Vec<String>> {
+    let code = match runtime {
+        "ruby" => ["-e", "require ARGV[0]", "--"].as_slice(),
+        "python3" | "python" => ["-c", "import importlib, sys; importlib.import_module(sys.argv[1])"].as_slice(),
+        "node" => ["-e", "require(process.argv[1])"].as_slice(),
+        _ => return None,
+    };
+    Some(code.iter().map(|arg| arg.to_string()).chain([package.to_string()]).collect())
+}
+
+/// Is `package` loadable by `runtime`? `None` when forge cannot tell.
+pub fn package_available(runtime: &str, package: &str) -> Option<bool> {
+    let args = package_probe(runtime, package)?;
+    Some(Command::new(runtime).args(args).output().is_ok_and(|o| o.status.success()))
+}
+
+/// How to install `package` for `runtime`.
+pub fn package_install_hint(runtime: &str, package: &str) -> String {
+    match runtime {
+        "ruby" => format!("gem install {}", package),
+        "python3" | "python" => format!("python3 -m pip install {}", package),
+        "node" => format!("npm install {}", package),
+        _ => format!("install {} for {}", package, runtime),
+    }
+}
+
+/// Is `program` a path to an existing file, or a command on `PATH`?
+pub fn program_exists(program: &str, cwd: Option<&Path>) -> bool {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return match cwd {
+            Some(cwd) => cwd.join(path).is_file(),
+            None => path.is_file(),
+        };
+    }
+    let extensions: &[&str] = if cfg!(windows) { &["", ".exe", ".cmd", ".bat"] } else { &[""] };
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            extensions.iter().any(|ext| dir.join(format!("{}{}", program, ext)).is_file())
+        })
+    })
+}
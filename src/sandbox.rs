@@ -0,0 +1,90 @@
+// src/sandbox.rs
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::Result;
+
+use crate::context::ForgeContext;
+use crate::plugin::PluginMetadata;
+use crate::trust::{TrustLevel, TrustStore};
+
+/// System directories mounted read-only so runtimes (ruby, python3, node) and
+/// their libraries keep working inside the sandbox.
+const SYSTEM_DIRS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib64", "/lib32", "/etc", "/opt", "/nix/store"];
+
+/// Paths a sandboxed process may see.
+#[derive(Debug, Default)]
+pub struct SandboxSpec {
+    pub read_only: Vec<PathBuf>,
+    pub writable: Vec<PathBuf>,
+    pub workdir: Option<PathBuf>,
+}
+
+/// Should processes run on behalf of `plugin` be sandboxed?
+pub fn sandbox_required(ctx: &ForgeContext, plugin: Option<&PluginMetadata>) -> Result<bool> {
+    if ctx.sandbox {
+        return Ok(true);
+    }
+    let Some(plugin) = plugin else {
+        return Ok(false);
+    };
+    Ok(TrustStore::load(ctx)?.level(ctx, &plugin.name) == TrustLevel::Sandboxed)
+}
+
+/// Build the command for `program args`, wrapped in a bubblewrap sandbox when
+/// the plugin's trust level asks for one.
+///
+/// Inside the sandbox the process has no network, sees the system directories
+/// and `spec.read_only` read-only, and can write only `spec.writable`. Every
+/// writable path must already exist.
+pub fn sandboxed_command(ctx: &ForgeContext, plugin: Option<&PluginMetadata>, program: &str, args: &[&str], spec: &SandboxSpec) -> Result<Command> {
+    if !sandbox_required(ctx, plugin)? {
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(workdir) = &spec.workdir {
+            cmd.current_dir(workdir);
+        }
+        return Ok(cmd);
+    }
+
+    ensure_available()?;
+
+    let mut cmd = Command::new("bwrap");
+    cmd.args(["--unshare-all", "--die-with-parent", "--new-session"]);
+    for dir in SYSTEM_DIRS {
+        if Path::new(dir).exists() {
+            cmd.args(["--ro-bind", dir, dir]);
+        }
+    }
+    cmd.args(["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"]);
+
+    for path in &spec.read_only {
+        let path = absolute(path)?;
+        cmd.arg("--ro-bind").arg(&path).arg(&path);
+    }
+    for path in &spec.writable {
+        let path = absolute(path)?;
+        cmd.arg("--bind").arg(&path).arg(&path);
+    }
+    if let Some(workdir) = &spec.workdir {
+        cmd.arg("--chdir").arg(absolute(workdir)?);
+    }
+
+    cmd.arg("--").arg(program).args(args);
+    Ok(cmd)
+}
+
+/// Fail unless this host can sandbox processes.
+pub fn ensure_available() -> Result<()> {
+    if !cfg!(target_os = "linux") {
+        anyhow::bail!("Sandboxed execution is only supported on Linux");
+    }
+    let available = Command::new("bwrap").arg("--version").output().is_ok_and(|o| o.status.success());
+    if !available {
+        anyhow::bail!("Sandboxed execution requires bubblewrap (`bwrap`), which was not found. Install it or lower the plugin's trust level with `forge trust set`.");
+    }
+    Ok(())
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    Ok(std::path::absolute(path)?)
+}
@@ -0,0 +1,271 @@
+// src/secrets.rs
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::process::Command;
+use std::sync::Mutex;
+use anyhow::{Result, Context};
+
+use crate::context::ForgeContext;
+
+const REDACTED: &str = "[redacted]";
+
+/// Values shorter than this are not redacted, so a one-character secret
+/// cannot blank out every message.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// Every secret value resolved by this process, for `redact`.
+static RESOLVED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Where a secret comes from, written as a reference in the manifest so the
+/// value itself never is:
+///
+/// * `env:NAME` — an environment variable
+/// * `file:NAME` — an entry of the secrets file (`secrets.json` in forge's data directory)
+/// * `pass:path/in/store` — the first line of `pass show`
+/// * `op://vault/item/field` — `op read` (1Password)
+/// * `secret-tool:attribute=value ...` — `secret-tool lookup` (GNOME Keyring, KWallet)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    Env(String),
+    File(String),
+    Pass(String),
+    OnePassword(String),
+    SecretTool(Vec<(String, String)>),
+}
+
+impl SecretRef {
+    pub fn parse(reference: &str) -> Result<SecretRef> {
+        let reference = reference.trim();
+        let invalid = || anyhow::anyhow!(
+            "'{}' is not a secret reference; use env:NAME, file:NAME, pass:PATH, op://VAULT/ITEM/FIELD or secret-tool:ATTRIBUTE=VALUE",
+            mask(reference));
+        if reference.starts_with("op://") {
+            return Ok(SecretRef::OnePassword(reference.to_string()));
+        }
+        let (scheme, rest) = reference.split_once(':').ok_or_else(invalid)?;
+        let rest = rest.trim();
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        match scheme {
+            "env" => Ok(SecretRef::Env(rest.to_string())),
+            "file" => Ok(SecretRef::File(rest.to_string())),
+            "pass" => Ok(SecretRef::Pass(rest.to_string())),
+            "secret-tool" => {
+                let attributes = rest.split_whitespace()
+                    .map(|pair| pair.split_once('=')
+                        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+                        .map(|(key, value)| (key.to_string(), value.to_string())))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(invalid)?;
+                Ok(SecretRef::SecretTool(attributes))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Look the secret up; `None` when the variable or file entry is not set.
+    /// Password manager lookups that find nothing are errors.
+    pub fn resolve(&self, ctx: &ForgeContext) -> Result<Option<String>> {
+        let value = match self {
+            SecretRef::Env(name) => std::env::var(name).ok().filter(|v| !v.is_empty()),
+            SecretRef::File(name) => load_secrets_file(ctx)?.remove(name),
+            SecretRef::Pass(path) => {
+                let out = lookup_command("pass", &["show", path])?;
+                out.lines().next().map(str::to_string)
+            }
+            SecretRef::OnePassword(reference) => Some(lookup_command("op", &["read", "--no-newline", reference])?),
+            SecretRef::SecretTool(attributes) => {
+                let mut args = vec!["lookup"];
+                for (key, value) in attributes {
+                    args.push(key);
+                    args.push(value);
+                }
+                Some(lookup_command("secret-tool", &args)?)
+            }
+        };
+        let value = value.map(|v| v.trim_end_matches(['\r', '\n']).to_string()).filter(|v| !v.is_empty());
+        if let Some(value) = &value {
+            remember(value);
+        }
+        Ok(value)
+    }
+}
+
+/// Resolve the secret `name` from the environment variable `name`, then
+/// through `reference` when one is configured, else from the secrets file.
+pub fn resolve_named(ctx: &ForgeContext, name: &str, reference: Option<&str>) -> Result<Option<String>> {
+    if let Some(value) = SecretRef::Env(name.to_string()).resolve(ctx)? {
+        return Ok(Some(value));
+    }
+    match reference {
+        Some(reference) => {
+            let reference = SecretRef::parse(reference).with_context(|| format!("Invalid reference for secret {}", name))?;
+            reference.resolve(ctx).with_context(|| format!("Failed to look up secret {}", name))
+        }
+        None => SecretRef::File(name.to_string()).resolve(ctx),
+    }
+}
+
+/// Resolve every secret in `names`, failing with all the missing ones at once.
+pub fn resolve_all(ctx: &ForgeContext, names: &[String], references: &BTreeMap<String, String>) -> Result<Vec<(String, String)>> {
+    let mut resolved = Vec::new();
+    let mut missing = Vec::new();
+    for name in names {
+        match resolve_named(ctx, name, references.get(name).map(String::as_str))? {
+            Some(value) => resolved.push((name.clone(), value)),
+            None => missing.push(name.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Secret(s) {} not found. Set them in the environment or {}, or map them to a password manager in the secrets of .forge/manifest.json (e.g. \"pass:path/in/store\").",
+            missing.join(", "), ctx.paths.secrets_file().display());
+    }
+    Ok(resolved)
+}
+
+/// Replace every secret resolved so far with `[redacted]`.
+pub fn redact(text: &str) -> String {
+    let resolved = RESOLVED.lock().unwrap_or_else(|e| e.into_inner());
+    let mut text = text.to_string();
+    for value in resolved.iter() {
+        if text.contains(value.as_str()) {
+            text = text.replace(value.as_str(), REDACTED);
+        }
+    }
+    text
+}
+
+pub(crate) fn remember(value: &str) {
+    if value.len() < MIN_REDACTED_LEN {
+        return;
+    }
+    let mut resolved = RESOLVED.lock().unwrap_or_else(|e| e.into_inner());
+    if !resolved.iter().any(|v| v == value) {
+        resolved.push(value.to_string());
+        // Longest first, so a secret containing another is redacted whole
+        resolved.sort_by_key(|v| std::cmp::Reverse(v.len()));
+    }
+}
+
+/// A reference that was mistaken for a value is shown only by its start.
+fn mask(text: &str) -> String {
+    match text.char_indices().nth(4) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Name → value file for secrets kept outside a password manager. forge
+/// refuses to read it unless only its owner can (`chmod 600`).
+fn load_secrets_file(ctx: &ForgeContext) -> Result<HashMap<String, String>> {
+    let path = ctx.paths.secrets_file();
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            anyhow::bail!("{} can be read by other users (mode {:o}); run `chmod 600 {}`",
+                path.display(), mode & 0o777, path.display());
+        }
+    }
+    let content = fs::read_to_string(&path)?;
+    let secrets: HashMap<String, String> = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}; it must map secret names to values", path.display()))?;
+    for value in secrets.values() {
+        remember(value);
+    }
+    Ok(secrets)
+}
+
+fn lookup_command(program: &str, args: &[&str]) -> Result<String> {
+    let out = Command::new(program).args(args).output()
+        .with_context(|| format!("Failed to run {}; is it installed?", program))?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        anyhow::bail!("{} {} failed: {}", program, args.first().copied().unwrap_or_default(), stderr.trim());
+    }
+    let value = String::from_utf8(out.stdout).with_context(|| format!("{} printed a secret that is not UTF-8", program))?;
+    if value.trim().is_empty() {
+        anyhow::bail!("{} {} printed nothing", program, args.first().copied().unwrap_or_default());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_reads_every_scheme() {
+        assert_eq!(SecretRef::parse("env:API_TOKEN").unwrap(), SecretRef::Env("API_TOKEN".to_string()));
+        assert_eq!(SecretRef::parse(" file:npm ").unwrap(), SecretRef::File("npm".to_string()));
+        assert_eq!(SecretRef::parse("pass:work/npm").unwrap(), SecretRef::Pass("work/npm".to_string()));
+        assert_eq!(SecretRef::parse("op://vault/npm/token").unwrap(), SecretRef::OnePassword("op://vault/npm/token".to_string()));
+        assert_eq!(SecretRef::parse("secret-tool:service=npm user=me").unwrap(), SecretRef::SecretTool(vec![
+            ("service".to_string(), "npm".to_string()),
+            ("user".to_string(), "me".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn parse_rejects_values_without_echoing_them() {
+        for reference in ["ghp_0123456789abcdef", "vault:thing", "env:", "secret-tool:=npm", "secret-tool:service=", "secret-tool:service npm"] {
+            let err = SecretRef::parse(reference).unwrap_err().to_string();
+            assert!(err.contains("is not a secret reference"), "{}", err);
+            if reference.len() > 4 {
+                assert!(!err.contains(reference), "{}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn redact_replaces_resolved_values() {
+        remember("hunter2-redact-test");
+        remember("abc");
+        assert_eq!(redact("password hunter2-redact-test, pin abc"), "password [redacted], pin abc");
+    }
+
+    #[test]
+    fn redact_replaces_the_longest_secret_first() {
+        remember("tok-redact-long");
+        remember("tok-redact-long-suffix");
+        assert_eq!(redact("x tok-redact-long-suffix y tok-redact-long"), "x [redacted] y [redacted]");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_secrets_need_private_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let path = ctx.paths.secrets_file();
+        fs::write(&path, r#"{"NPM_TOKEN": "npm-file-secret"}"#).unwrap();
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let err = SecretRef::File("NPM_TOKEN".to_string()).resolve(&ctx).unwrap_err();
+        assert!(err.to_string().contains("chmod 600"), "{}", err);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(SecretRef::File("NPM_TOKEN".to_string()).resolve(&ctx).unwrap().as_deref(), Some("npm-file-secret"));
+        assert_eq!(SecretRef::File("OTHER".to_string()).resolve(&ctx).unwrap(), None);
+        assert_eq!(redact("token npm-file-secret"), "token [redacted]");
+    }
+
+    #[test]
+    fn resolve_all_names_every_missing_secret() {
+        let home = TempDir::new().unwrap();
+        let ctx = ForgeContext::for_tests(home.path());
+        let names = vec!["FORGE_TEST_MISSING_A".to_string(), "FORGE_TEST_MISSING_B".to_string()];
+
+        let err = resolve_all(&ctx, &names, &BTreeMap::new()).unwrap_err().to_string();
+        assert!(err.starts_with("Secret(s) FORGE_TEST_MISSING_A, FORGE_TEST_MISSING_B not found"), "{}", err);
+        assert!(err.contains(&ctx.paths.secrets_file().display().to_string()));
+    }
+}
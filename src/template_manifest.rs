@@ -0,0 +1,63 @@
+// src/template_manifest.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::ai_generated::AiGeneratedFile;
+use crate::prompts::PromptAsset;
+use crate::runtime_checker::RuntimeConstraint;
+
+/// File at the root of a template tree describing the template itself. It is
+/// never rendered into the output.
+pub const TEMPLATE_MANIFEST: &str = "forge-template.json";
+
+/// Default time a hook may run before it is killed.
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 300;
+
+/// Contents of `forge-template.json`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TemplateManifest {
+    #[serde(default)]
+    pub hooks: TemplateHooks,
+    /// Runtimes the templates need, checked together with the plugin's.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, RuntimeConstraint>,
+    /// Files written by an LLM after the templates are rendered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ai_generated: Vec<AiGeneratedFile>,
+    /// Named prompt assets, preferred over the plugin's of the same name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompts: BTreeMap<String, PromptAsset>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TemplateHooks {
+    /// Run before rendering; a failure rolls the output directory back.
+    #[serde(default)]
+    pub pre_generate: Vec<Hook>,
+    /// Run after every file has been rendered.
+    #[serde(default)]
+    pub post_generate: Vec<Hook>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Hook {
+    /// Shell command, run with the output directory as working directory.
+    pub command: String,
+    /// Seconds before the hook is killed (default 300).
+    pub timeout_secs: Option<u64>,
+}
+
+impl TemplateManifest {
+    /// Load `forge-template.json` from a template root, or an empty manifest if absent.
+    pub fn load(template_root: &Path) -> Result<TemplateManifest> {
+        let path = template_root.join(TEMPLATE_MANIFEST);
+        if !path.exists() {
+            return Ok(TemplateManifest::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
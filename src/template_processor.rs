@@ -1,50 +1,302 @@
 // src/template_processor.rs
+use std::collections::HashMap;
 use std::fs;
-use std::path::{PathBuf};
-use anyhow::{Result};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use clap::ValueEnum;
 use walkdir::WalkDir;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::ai_generated::generate_file;
+use crate::context::ForgeContext;
+use crate::hooks::{run_hook, HookResult};
+use crate::llm::{configured_provider, LLM_PROVIDER_ENV};
+use crate::llm_cache::LlmMode;
 use crate::plugin::PluginMetadata;
-use crate::runtime_checker::check_required_runtimes;
-use crate::renderer::render_template_file;
+use crate::prompts::{PromptLibrary, ResolvedPrompt};
+use crate::render_agent::{bundle_path, BundleFile, RenderAgent, RenderPlan};
+use crate::runtime_checker::{check_required_runtimes, declared_runtimes};
+use crate::renderer::EngineRegistry;
+use crate::template_manifest::{TemplateManifest, TEMPLATE_MANIFEST};
+
+/// A single template rendered by `process_templates`.
+#[derive(Debug, Serialize)]
+pub struct RenderedFile {
+    /// Template path, or `forge-template.json` for a file only an LLM wrote.
+    pub template: PathBuf,
+    pub output: PathBuf,
+    /// `provider:model` of the LLM that wrote the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_by: Option<String>,
+    /// `name@version` of the prompt asset it was written from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// Summary of a `forge generate` run.
+#[derive(Debug, Serialize)]
+pub struct GenerateSummary {
+    pub plugin: String,
+    pub plugin_version: String,
+    pub templates_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub files: Vec<RenderedFile>,
+    /// Existing files left alone by `--on-conflict skip`.
+    pub skipped: Vec<PathBuf>,
+    pub hooks: Vec<HookResult>,
+}
+
+/// What `forge generate` does with an output file that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Replace it (the default).
+    #[default]
+    Overwrite,
+    /// Keep it; the template or model does not write it.
+    Skip,
+    /// Stop before any hook runs or file is written.
+    Fail,
+}
+
+/// Variables available to templates and lifecycle actions, read from
+/// `.forge/variables.json` in the project directory.
+pub fn load_variables(project_dir: &Path) -> Result<Value> {
+    let path = project_dir.join(".forge/variables.json");
+    if !path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let content = fs::read_to_string(&path)?;
+    let variables: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    if !variables.is_object() {
+        anyhow::bail!("{} must contain a JSON object", path.display());
+    }
+    Ok(variables)
+}
 
 /// Process and render templates from a directory
 pub fn process_templates(
+    ctx: &ForgeContext,
     plugin: &PluginMetadata,
     templates_dir: Option<PathBuf>,
     output_dir: PathBuf,
-) -> Result<()> {
+    on_conflict: ConflictPolicy,
+) -> Result<GenerateSummary> {
     let template_root = match templates_dir {
         Some(dir) => dir,
-        None => plugin.templates.clone()
+        None => PathBuf::from(&plugin.templates)
     };
 
+    let manifest = TemplateManifest::load(&template_root)?;
+    // The manifest and its prompt files describe the template; they are not rendered
+    let mut metadata_paths = vec![template_root.join(TEMPLATE_MANIFEST)];
+    metadata_paths.extend(manifest.prompts.values().map(|prompt| template_root.join(&prompt.file)));
+
     let mut template_paths = Vec::new();
-    for entry in WalkDir::new(&template_root) {
+    for entry in WalkDir::new(&template_root).into_iter().filter_entry(|e| e.file_name() != ".git") {
         let entry = entry?;
         let path = entry.path();
-        if path.is_file() {
+        if path.is_file() && !metadata_paths.iter().any(|p| p == path) {
             template_paths.push(path.to_path_buf());
         }
     }
+    template_paths.sort();
 
-    // Check required runtimes
-    check_required_runtimes(&template_paths, Some(plugin))?;
+    // Check required runtimes before any hook runs or file is written; the
+    // render agent, if any, checks those of the files it renders
+    let engines = EngineRegistry::for_plugin(ctx, Some(plugin));
+    let agent = RenderAgent::configured(ctx, &std::env::current_dir()?)?;
+    let plan = RenderPlan::new(agent.as_ref(), &engines, &template_paths, declared_runtimes(Some(plugin), Some(&manifest)));
+    check_required_runtimes(&engines, &plan.local, &plan.local_runtimes)?;
+    if let Some(agent) = &agent && !plan.remote.is_empty() {
+        agent.check(plan.remote_extensions(), &plan.remote_runtimes)?;
+    }
+    let llm = match manifest.ai_generated.is_empty() {
+        true => None,
+        false => configured_provider(ctx, &std::env::current_dir()?)?,
+    };
+    let prompts = PromptLibrary { plugin, template: &manifest, template_root: &template_root };
+    if llm.is_some() {
+        for entry in &manifest.ai_generated {
+            if let Some(problem) = entry.problems().first() {
+                anyhow::bail!("Invalid ai_generated entry '{}' in {}: {}", entry.path, TEMPLATE_MANIFEST, problem);
+            }
+            if let Some(reference) = &entry.uses {
+                prompts.find(reference)?;
+            }
+        }
+    }
 
-    // TODO: Replace with real variable gathering mechanism
-    let variables: Value = serde_json::json!({});
+    // Files that exist before generation, which the conflict policy decides about
+    let mut outputs = Vec::new();
+    for input_path in &template_paths {
+        outputs.push(output_dir.join(input_path.strip_prefix(&template_root)?));
+    }
+    if llm.is_some() {
+        outputs.extend(manifest.ai_generated.iter().map(|entry| output_dir.join(&entry.path)));
+    }
+    let mut existing: Vec<PathBuf> = outputs.into_iter().filter(|path| path.exists()).collect();
+    existing.sort();
+    existing.dedup();
+    let kept = match on_conflict {
+        ConflictPolicy::Overwrite => Vec::new(),
+        ConflictPolicy::Skip => existing,
+        ConflictPolicy::Fail if existing.is_empty() => Vec::new(),
+        ConflictPolicy::Fail => anyhow::bail!(
+            "{} file(s) already exist in {}: {}\nPass --on-conflict overwrite or skip, or set generate.on_conflict.",
+            existing.len(), output_dir.display(),
+            existing.iter().map(|p| p.strip_prefix(&output_dir).unwrap_or(p).display().to_string()).collect::<Vec<_>>().join(", ")),
+    };
+
+    // TODO: Prompt for missing variables instead of relying on the file alone
+    let mut variables: Value = load_variables(&std::env::current_dir()?)?;
+
+    let hooks = &manifest.hooks;
+    let mut hook_results = Vec::new();
+    if !hooks.pre_generate.is_empty() {
+        let snapshot = OutputSnapshot::take(&output_dir)?;
+        for hook in &hooks.pre_generate {
+            match run_hook(ctx, hook, "pre_generate", plugin, &template_root, &output_dir, &mut variables) {
+                Ok(result) => hook_results.push(result),
+                Err(err) => {
+                    snapshot.restore(&output_dir)
+                        .context("Failed to roll back the output directory")?;
+                    return Err(err.context("Pre-generate hook failed; output directory rolled back"));
+                }
+            }
+        }
+    }
 
+    let mut remote_output: HashMap<String, String> = HashMap::new();
+    if let Some(agent) = &agent && !plan.remote.is_empty() {
+        let mut bundle = Vec::new();
+        for input_path in &plan.remote {
+            bundle.push(BundleFile {
+                path: bundle_path(input_path.strip_prefix(&template_root)?),
+                content: fs::read_to_string(input_path)
+                    .with_context(|| format!("Failed to read {:?} for the render agent", input_path))?,
+            });
+        }
+        remote_output = agent.render(bundle, &variables, &plan.remote_runtimes)?
+            .into_iter()
+            .map(|file| (file.path, file.content))
+            .collect();
+    }
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
     for input_path in &template_paths {
         let rel_path = input_path.strip_prefix(&template_root)?;
         let output_path = output_dir.join(rel_path);
+        if kept.contains(&output_path) {
+            skipped.push(output_path);
+            continue;
+        }
 
         if let Some(parent) = output_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        render_template_file(input_path, &output_path, &variables, Some(plugin))?
+        if plan.remote.contains(input_path) {
+            let content = remote_output.remove(&bundle_path(rel_path))
+                .with_context(|| format!("Render agent returned no output for {:?}", rel_path))?;
+            fs::write(&output_path, content)?;
+        } else {
+            engines.render(input_path, &output_path, &variables)?;
+        }
+        files.push(RenderedFile { template: rel_path.to_path_buf(), output: output_path, generated_by: None, prompt: None });
+    }
+
+    // After rendering, so every rendered file can serve as context
+    match &llm {
+        Some(llm) => {
+            for entry in &manifest.ai_generated {
+                let output_path = output_dir.join(&entry.path);
+                if kept.contains(&output_path) {
+                    if !skipped.contains(&output_path) {
+                        skipped.push(output_path);
+                    }
+                    continue;
+                }
+                let prompt = match (&entry.prompt, &entry.uses) {
+                    (Some(text), _) => ResolvedPrompt::inline(text, &variables),
+                    (None, Some(reference)) => prompts.resolve(reference, &engines, &variables)?,
+                    (None, None) => unreachable!("checked before rendering"),
+                };
+                let output_path = generate_file(llm.as_ref(), entry, &prompt, &output_dir)?;
+                match files.iter_mut().find(|f| f.output == output_path) {
+                    Some(file) => {
+                        file.generated_by = Some(llm.id());
+                        file.prompt = prompt.asset;
+                    }
+                    None => files.push(RenderedFile {
+                        template: PathBuf::from(TEMPLATE_MANIFEST),
+                        output: output_path,
+                        generated_by: Some(llm.id()),
+                        prompt: prompt.asset,
+                    }),
+                }
+            }
+        }
+        None if !manifest.ai_generated.is_empty() && ctx.llm == LlmMode::Off => {
+            eprintln!("⏭  --llm=off: skipping {} AI-generated file(s)", manifest.ai_generated.len());
+        }
+        None if !manifest.ai_generated.is_empty() => {
+            eprintln!("⚠️  Skipping {} AI-generated file(s): no LLM provider configured (set {} or `forge config set llm.provider`)",
+                manifest.ai_generated.len(), LLM_PROVIDER_ENV);
+        }
+        None => {}
+    }
+
+    if !hooks.post_generate.is_empty() {
+        fs::create_dir_all(&output_dir)?;
+    }
+    for hook in &hooks.post_generate {
+        hook_results.push(run_hook(ctx, hook, "post_generate", plugin, &template_root, &output_dir, &mut variables)?);
     }
 
-    Ok(())
+    Ok(GenerateSummary {
+        plugin: plugin.name.clone(),
+        plugin_version: plugin.version.clone(),
+        templates_dir: template_root,
+        output_dir,
+        files,
+        skipped,
+        hooks: hook_results,
+    })
+}
+
+/// Copy of the output directory taken before pre-generate hooks run, so a
+/// failing hook leaves it exactly as it was.
+struct OutputSnapshot {
+    backup: Option<tempfile::TempDir>,
+}
+
+impl OutputSnapshot {
+    fn take(output_dir: &Path) -> Result<OutputSnapshot> {
+        let backup = if output_dir.exists() {
+            let backup = tempfile::tempdir()?;
+            fs_extra::dir::copy(output_dir, backup.path(), &fs_extra::dir::CopyOptions::new().content_only(true))
+                .with_context(|| format!("Failed to snapshot {}", output_dir.display()))?;
+            Some(backup)
+        } else {
+            None
+        };
+        fs::create_dir_all(output_dir)?;
+        Ok(OutputSnapshot { backup })
+    }
+
+    fn restore(self, output_dir: &Path) -> Result<()> {
+        if output_dir.exists() {
+            fs::remove_dir_all(output_dir)?;
+        }
+        if let Some(backup) = self.backup {
+            fs::create_dir_all(output_dir)?;
+            fs_extra::dir::copy(backup.path(), output_dir, &fs_extra::dir::CopyOptions::new().content_only(true))
+                .with_context(|| format!("Failed to restore {}", output_dir.display()))?;
+        }
+        Ok(())
+    }
 }
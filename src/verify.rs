@@ -0,0 +1,272 @@
+// src/verify.rs
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use minisign_verify::{PublicKey, Signature};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+/// Outcome of checking an installed plugin or cloned template against the registry.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    /// SHA-256 of the directory contents, see `digest_dir`.
+    pub sha256: String,
+    /// Checksum the registry declares for this version, if any.
+    pub expected_sha256: Option<String>,
+    /// File name of the trusted key that verified the signature, if one did.
+    pub signed_by: Option<String>,
+    /// Why verification failed, if it did.
+    pub problem: Option<String>,
+}
+
+impl Verification {
+    pub fn is_verified(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Compute a stable SHA-256 over every file below `root`.
+///
+/// Files are visited in sorted order and `.git` is skipped, so a fresh clone
+/// and a reinstalled copy of the same commit hash identically.
+pub fn digest_dir(root: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let entries = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel_path = entry.path().strip_prefix(root)?;
+        let content = fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        hasher.update(rel_path.to_string_lossy().replace('\\', "/").as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// The minisign public keys (`*.pub`) in `dir`, by file name.
+fn load_trusted_keys(dir: &Path) -> Result<Vec<(String, PublicKey)>> {
+    let mut keys = Vec::new();
+    if !dir.exists() {
+        return Ok(keys);
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("pub"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let key = PublicKey::from_file(&path)
+            .map_err(|e| anyhow::anyhow!("Invalid trusted key {}: {}", path.display(), e))?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        keys.push((name, key));
+    }
+    Ok(keys)
+}
+
+/// Verify `dir` against the registry checksum and, when present, a minisign
+/// signature over the hex digest made by a key in `keys_dir`.
+pub fn verify_dir(dir: &Path, expected_sha256: Option<&str>, signature: Option<&str>, keys_dir: &Path) -> Result<Verification> {
+    let sha256 = digest_dir(dir)?;
+    let mut verification = Verification {
+        sha256: sha256.clone(),
+        expected_sha256: expected_sha256.map(String::from),
+        signed_by: None,
+        problem: None,
+    };
+
+    match expected_sha256 {
+        None => verification.problem = Some("no checksum published for this version".to_string()),
+        Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+            verification.problem = Some(format!("checksum mismatch: expected {}, got {}", expected, sha256));
+        }
+        Some(_) => {}
+    }
+
+    if let Some(signature) = signature {
+        let signature = Signature::decode(signature)
+            .map_err(|e| anyhow::anyhow!("Invalid signature: {}", e))?;
+        let keys = load_trusted_keys(keys_dir)?;
+        verification.signed_by = keys.iter()
+            .find(|(_, key)| key.verify(sha256.as_bytes(), &signature, false).is_ok())
+            .map(|(name, _)| name.clone());
+        if verification.signed_by.is_none() && verification.problem.is_none() {
+            verification.problem = Some(format!("signature not made by a key in {}", keys_dir.display()));
+        }
+    }
+
+    Ok(verification)
+}
+
+/// Fail unless `verification` passed or the user opted out with `--allow-unverified`.
+pub fn require_verified(what: &str, verification: &Verification, allow_unverified: bool) -> Result<()> {
+    if let Some(problem) = &verification.problem {
+        if !allow_unverified {
+            anyhow::bail!("Refusing to use unverified {}: {}. Pass --allow-unverified to override.", what, problem);
+        }
+        eprintln!("⚠️  Using unverified {}: {}", what, problem);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn plugin_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("plugin.json"), r#"{"name":"demo"}"#).unwrap();
+        fs::create_dir(dir.path().join("templates")).unwrap();
+        fs::write(dir.path().join("templates/README.md"), "# {{name}}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn matching_checksum_verifies() {
+        let dir = plugin_dir();
+        let digest = digest_dir(dir.path()).unwrap();
+        let verification = verify_dir(dir.path(), Some(&digest.to_uppercase()), None, dir.path()).unwrap();
+        assert!(verification.is_verified(), "{:?}", verification.problem);
+        assert_eq!(verification.sha256, digest);
+    }
+
+    #[test]
+    fn changed_file_is_a_checksum_mismatch() {
+        let dir = plugin_dir();
+        let expected = digest_dir(dir.path()).unwrap();
+        fs::write(dir.path().join("templates/README.md"), "# changed").unwrap();
+
+        let verification = verify_dir(dir.path(), Some(&expected), None, dir.path()).unwrap();
+        let problem = verification.problem.unwrap();
+        assert!(problem.starts_with("checksum mismatch"), "{}", problem);
+        assert!(problem.contains(&expected));
+        assert_ne!(verification.sha256, expected);
+    }
+
+    #[test]
+    fn renamed_file_is_a_checksum_mismatch() {
+        let dir = plugin_dir();
+        let expected = digest_dir(dir.path()).unwrap();
+        fs::rename(dir.path().join("templates/README.md"), dir.path().join("templates/README.txt")).unwrap();
+
+        assert!(!verify_dir(dir.path(), Some(&expected), None, dir.path()).unwrap().is_verified());
+    }
+
+    #[test]
+    fn missing_checksum_is_a_problem() {
+        let dir = plugin_dir();
+        let verification = verify_dir(dir.path(), None, None, dir.path()).unwrap();
+        assert_eq!(verification.problem.as_deref(), Some("no checksum published for this version"));
+    }
+
+    #[test]
+    fn git_directory_is_ignored() {
+        let dir = plugin_dir();
+        let expected = digest_dir(dir.path()).unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+
+        assert!(verify_dir(dir.path(), Some(&expected), None, dir.path()).unwrap().is_verified());
+    }
+
+    /// `digest_dir(plugin_dir())`, signed with minisign (prehashed) by the
+    /// secret key of `SIGNING_KEY`; `OTHER_KEY` did not sign it.
+    const SIGNED_DIGEST: &str = "3ed7ec0f948d661c533d0ad6fec0f1a27fd9c0287d0206845bb98ff678abeece";
+    const SIGNING_KEY: &str = "untrusted comment: minisign public key 0102030405060708
+RWQBAgMEBQYHCIqI4910CfGV/VLbLTy6XXLKZwm/HZQSG/N0iAG0D29c
+";
+    const OTHER_KEY: &str = "untrusted comment: minisign public key 1112131415161718
+RWQREhMUFRYXGIE5dw6ofRdfVqNUZsNMfszLjYqRtO43ol32D1uPybOU
+";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBAgMEBQYHCNHlGQYLVYzpHpmjk0WbxFlej3uuU4L51ojkidhZvLQzZXAvz+RRtwEvlK6RjYqhYXwyVDgDh9dZ2NxFmxxJBwo=
+trusted comment: timestamp:1767225600\tfile:demo
+LKBIsWTkseBHExYIe4dtCF/0OiDsg7srM4zfyzZwGmE9iOFrKrIX9fUcw8eMlhHuj2fEaW/EK35E6vKQH9VGBQ==
+";
+
+    fn keys_dir(keys: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (name, key) in keys {
+            fs::write(dir.path().join(name), key).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn loads_pub_files_sorted_by_name() {
+        let keys = keys_dir(&[("b.pub", OTHER_KEY), ("a.pub", SIGNING_KEY), ("notes.txt", "not a key")]);
+        let names: Vec<String> = load_trusted_keys(keys.path()).unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["a.pub", "b.pub"]);
+
+        assert!(load_trusted_keys(&keys.path().join("missing")).unwrap().is_empty());
+        fs::write(keys.path().join("broken.pub"), "untrusted comment: x\nnot base64").unwrap();
+        assert!(load_trusted_keys(keys.path()).unwrap_err().to_string().contains("broken.pub"));
+    }
+
+    #[test]
+    fn signature_by_a_trusted_key_verifies() {
+        let dir = plugin_dir();
+        assert_eq!(digest_dir(dir.path()).unwrap(), SIGNED_DIGEST);
+        let keys = keys_dir(&[("other.pub", OTHER_KEY), ("forge.pub", SIGNING_KEY)]);
+
+        let verification = verify_dir(dir.path(), Some(SIGNED_DIGEST), Some(SIGNATURE), keys.path()).unwrap();
+        assert!(verification.is_verified(), "{:?}", verification.problem);
+        assert_eq!(verification.signed_by.as_deref(), Some("forge.pub"));
+    }
+
+    #[test]
+    fn signature_by_an_untrusted_key_fails() {
+        let dir = plugin_dir();
+        let keys = keys_dir(&[("other.pub", OTHER_KEY)]);
+
+        let verification = verify_dir(dir.path(), Some(SIGNED_DIGEST), Some(SIGNATURE), keys.path()).unwrap();
+        assert_eq!(verification.signed_by, None);
+        assert!(verification.problem.unwrap().starts_with("signature not made by a key in"));
+    }
+
+    #[test]
+    fn tampered_payload_fails_checksum_and_signature() {
+        let dir = plugin_dir();
+        fs::write(dir.path().join("templates/README.md"), "# {{name}} and a backdoor").unwrap();
+        let keys = keys_dir(&[("forge.pub", SIGNING_KEY)]);
+
+        // Even a checksum matching the tampered tree does not make the signature valid
+        let tampered = digest_dir(dir.path()).unwrap();
+        let verification = verify_dir(dir.path(), Some(&tampered), Some(SIGNATURE), keys.path()).unwrap();
+        assert_eq!(verification.signed_by, None);
+        assert!(verification.problem.unwrap().starts_with("signature not made by a key in"));
+
+        let verification = verify_dir(dir.path(), Some(SIGNED_DIGEST), Some(SIGNATURE), keys.path()).unwrap();
+        assert_eq!(verification.signed_by, None);
+        assert!(verification.problem.unwrap().starts_with("checksum mismatch"));
+    }
+
+    #[test]
+    fn malformed_signature_is_an_error() {
+        let dir = plugin_dir();
+        let err = verify_dir(dir.path(), Some(SIGNED_DIGEST), Some("not a signature"), dir.path()).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid signature"), "{}", err);
+    }
+
+    #[test]
+    fn require_verified_refuses_unless_allowed() {
+        let dir = plugin_dir();
+        let verification = verify_dir(dir.path(), Some("00"), None, dir.path()).unwrap();
+        let err = require_verified("plugin demo", &verification, false).unwrap_err();
+        assert!(err.to_string().contains("--allow-unverified"));
+        require_verified("plugin demo", &verification, true).unwrap();
+    }
+}
@@ -0,0 +1,466 @@
+// src/wasm_renderer.rs
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::renderer::{Capabilities, Renderer};
+use wasmi::{Caller, Config, Engine, Extern, ExternType, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Val};
+
+/// Host functions a renderer module may import.
+const FORGE_MODULE: &str = "forge";
+/// WASI imports are stubbed: stdout/stderr become diagnostics, everything
+/// that would touch the filesystem, network or environment fails.
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+/// WASI functions given a real (if restricted) implementation.
+const WASI_PROVIDED: &[&str] = &["fd_write", "proc_exit", "environ_sizes_get", "args_sizes_get", "environ_get", "args_get", "random_get"];
+const WASI_ERRNO_BADF: i32 = 8;
+const WASI_ERRNO_NOSYS: i32 = 52;
+
+/// Upper bound on the instructions a module may execute per template.
+const FUEL_PER_RENDER: u64 = 500_000_000;
+/// Upper bound on a module's linear memory.
+const MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+/// Upper bound on what a module may write per template, so the host cannot be
+/// made to buffer more than the guest itself may hold.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
+/// Upper bound on diagnostics (including stdout and stderr) per template.
+const MAX_DIAGNOSTIC_BYTES: usize = 1024 * 1024;
+/// Upper bound on the buffers of one `fd_write` call.
+const MAX_IOVS: u32 = 1024;
+
+/// A `.wasm` renderer declared in `plugin.json`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WasmRendererSpec {
+    /// Path to the module, relative to the plugin directory.
+    pub module: String,
+    /// Extensions the module owns; empty means every extension without a built-in engine.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+/// Engine for a plugin's `wasm_renderer`.
+pub struct WasmEngine<'a> {
+    spec: &'a WasmRendererSpec,
+}
+
+impl<'a> WasmEngine<'a> {
+    pub fn new(spec: &'a WasmRendererSpec) -> WasmEngine<'a> {
+        WasmEngine { spec }
+    }
+}
+
+impl Renderer for WasmEngine<'_> {
+    fn name(&self) -> String {
+        let module = Path::new(&self.spec.module);
+        format!("wasm:{}", module.file_name().unwrap_or(module.as_os_str()).to_string_lossy())
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        self.spec.extensions.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { in_process: true, ..Capabilities::default() }
+    }
+
+    /// Render in-process, reporting the module's diagnostics
+    fn render(&self, input_path: &Path, output_path: &Path, variables: &Value) -> Result<()> {
+        let template = fs::read(input_path)?;
+        let rendered = render_with_module(Path::new(&self.spec.module), &template, variables)
+            .with_context(|| format!("Failed to render {:?}", input_path))?;
+        for diagnostic in &rendered.diagnostics {
+            eprintln!("  {} {}: {}", diagnostic.level.label(), input_path.display(), diagnostic.message);
+        }
+        fs::write(output_path, rendered.output)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl DiagnosticLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            DiagnosticLevel::Info => "info",
+            DiagnosticLevel::Warning => "warning",
+            DiagnosticLevel::Error => "error",
+        }
+    }
+}
+
+/// A message reported by a renderer module.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    pub message: String,
+}
+
+/// Result of rendering one template with a module.
+#[derive(Debug, Default)]
+pub struct WasmRenderOutput {
+    pub output: Vec<u8>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+struct HostState {
+    output: Vec<u8>,
+    diagnostics: Vec<Diagnostic>,
+    diagnostic_bytes: usize,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    /// Fail before buffering `extra` more bytes of output.
+    fn reserve_output(&self, extra: usize) -> Result<(), wasmi::Error> {
+        within_cap(self.output.len(), extra, MAX_OUTPUT_BYTES, "output")
+    }
+
+    /// Fail before buffering `extra` more bytes of diagnostics.
+    fn reserve_diagnostics(&self, extra: usize) -> Result<(), wasmi::Error> {
+        within_cap(self.diagnostic_bytes, extra, MAX_DIAGNOSTIC_BYTES, "diagnostics")
+    }
+
+    fn push_diagnostic(&mut self, level: DiagnosticLevel, message: String) -> Result<(), wasmi::Error> {
+        self.reserve_diagnostics(message.len())?;
+        self.diagnostic_bytes += message.len();
+        self.diagnostics.push(Diagnostic { level, message });
+        Ok(())
+    }
+}
+
+fn within_cap(used: usize, extra: usize, cap: usize, what: &str) -> Result<(), wasmi::Error> {
+    match used.checked_add(extra) {
+        Some(total) if total <= cap => Ok(()),
+        _ => Err(wasmi::Error::new(format!("renderer wrote more than {} bytes of {}", cap, what))),
+    }
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, wasmi::Error> {
+    caller.get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("renderer module does not export `memory`"))
+}
+
+/// A length passed by the guest, which must not be negative.
+fn guest_len(len: i32) -> Result<u32, wasmi::Error> {
+    u32::try_from(len).map_err(|_| wasmi::Error::new(format!("negative length {}", len)))
+}
+
+/// The guest's memory and the byte range `ptr..ptr + len` in it, checked
+/// before the host allocates anything for it.
+fn guest_range(caller: &Caller<'_, HostState>, ptr: u32, len: u32) -> Result<(Memory, usize, usize), wasmi::Error> {
+    let memory = memory(caller)?;
+    let (start, len) = (ptr as usize, len as usize);
+    match start.checked_add(len) {
+        Some(end) if end <= memory.data(caller).len() => Ok((memory, start, end)),
+        _ => Err(wasmi::Error::new(format!("{} bytes at {:#x} are outside the module's memory", len, start))),
+    }
+}
+
+fn read_bytes(caller: &Caller<'_, HostState>, ptr: u32, len: u32) -> Result<Vec<u8>, wasmi::Error> {
+    let (memory, start, end) = guest_range(caller, ptr, len)?;
+    Ok(memory.data(caller)[start..end].to_vec())
+}
+
+fn write_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, bytes: &[u8]) -> Result<(), wasmi::Error> {
+    memory(caller)?.write(caller, ptr as u32 as usize, bytes)
+        .map_err(|err| wasmi::Error::new(err.to_string()))
+}
+
+fn linker(engine: &Engine, module: &Module) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(FORGE_MODULE, "output", |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let len = guest_len(len)?;
+        caller.data().reserve_output(len as usize)?;
+        let bytes = read_bytes(&caller, ptr as u32, len)?;
+        let mut caller = caller;
+        caller.data_mut().output.extend_from_slice(&bytes);
+        Ok(())
+    })?;
+    linker.func_wrap(FORGE_MODULE, "diagnostic", |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+        let len = guest_len(len)?;
+        caller.data().reserve_diagnostics(len as usize)?;
+        let message = String::from_utf8_lossy(&read_bytes(&caller, ptr as u32, len)?).into_owned();
+        let level = match level {
+            0 => DiagnosticLevel::Info,
+            1 => DiagnosticLevel::Warning,
+            _ => DiagnosticLevel::Error,
+        };
+        let mut caller = caller;
+        caller.data_mut().push_diagnostic(level, message)
+    })?;
+
+    linker.func_wrap(WASI_MODULE, "fd_write", |mut caller: Caller<'_, HostState>, fd: i32, iovs: i32, iovs_len: i32, nwritten: i32| {
+        if fd != 1 && fd != 2 {
+            return Ok(WASI_ERRNO_BADF);
+        }
+        let count = guest_len(iovs_len)?;
+        if count > MAX_IOVS {
+            return Err(wasmi::Error::new(format!("fd_write with {} buffers (at most {})", count, MAX_IOVS)));
+        }
+        let mut written = Vec::new();
+        for i in 0..count {
+            let address = i.checked_mul(8).and_then(|offset| (iovs as u32).checked_add(offset))
+                .ok_or_else(|| wasmi::Error::new(format!("fd_write buffer list at {:#x} overflows the address space", iovs as u32)))?;
+            let iov = read_bytes(&caller, address, 8)?;
+            let ptr = u32::from_le_bytes([iov[0], iov[1], iov[2], iov[3]]);
+            let len = u32::from_le_bytes([iov[4], iov[5], iov[6], iov[7]]);
+            caller.data().reserve_diagnostics(written.len().saturating_add(len as usize))?;
+            written.extend(read_bytes(&caller, ptr, len)?);
+        }
+        write_bytes(&mut caller, nwritten, &(written.len() as u32).to_le_bytes())?;
+        let text = String::from_utf8_lossy(&written);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            caller.data_mut().push_diagnostic(DiagnosticLevel::Info, line.to_string())?;
+        }
+        Ok(0)
+    })?;
+    linker.func_wrap(WASI_MODULE, "proc_exit", |_: Caller<'_, HostState>, code: i32| -> Result<(), wasmi::Error> {
+        Err(wasmi::Error::i32_exit(code))
+    })?;
+    // An empty environment and argument list
+    for name in ["environ_sizes_get", "args_sizes_get"] {
+        linker.func_wrap(WASI_MODULE, name, |mut caller: Caller<'_, HostState>, count: i32, size: i32| {
+            write_bytes(&mut caller, count, &0u32.to_le_bytes())?;
+            write_bytes(&mut caller, size, &0u32.to_le_bytes())?;
+            Ok(0)
+        })?;
+    }
+    for name in ["environ_get", "args_get"] {
+        linker.func_wrap(WASI_MODULE, name, |_: Caller<'_, HostState>, _: i32, _: i32| Ok(0))?;
+    }
+    // Deterministic "randomness" keeps rendering reproducible
+    linker.func_wrap(WASI_MODULE, "random_get", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let (memory, start, end) = guest_range(&caller, ptr as u32, guest_len(len)?)?;
+        memory.data_mut(&mut caller)[start..end].fill(0);
+        Ok(0)
+    })?;
+
+    // Every other WASI function the module imports is denied
+    for import in module.imports() {
+        if import.module() != WASI_MODULE || WASI_PROVIDED.contains(&import.name()) {
+            continue;
+        }
+        let ExternType::Func(ty) = import.ty() else { continue };
+        let name = import.name().to_string();
+        let returns_errno = ty.results() == [wasmi::core::ValType::I32];
+        linker.func_new(WASI_MODULE, import.name(), ty.clone(), move |_, _, results| {
+            if returns_errno {
+                results[0] = Val::I32(WASI_ERRNO_NOSYS);
+                Ok(())
+            } else {
+                Err(wasmi::Error::new(format!("WASI function {} is not available to renderers", name)))
+            }
+        })?;
+    }
+    Ok(linker)
+}
+
+fn load_module(engine: &Engine, module_path: &Path) -> Result<Module> {
+    let bytes = fs::read(module_path)
+        .with_context(|| format!("Failed to read WASM renderer {}", module_path.display()))?;
+    Module::new(engine, &bytes)
+        .with_context(|| format!("{} is not a valid WASM module", module_path.display()))
+}
+
+fn engine() -> Engine {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
+/// Check that a module compiles and exports the renderer interface.
+pub fn check_module(module_path: &Path) -> Result<()> {
+    let module = load_module(&engine(), module_path)?;
+    for (name, wanted) in [("memory", "memory"), ("forge_alloc", "function"), ("forge_render", "function")] {
+        let found = module.exports().find(|e| e.name() == name).map(|e| match e.ty() {
+            ExternType::Memory(_) => "memory",
+            ExternType::Func(_) => "function",
+            _ => "other",
+        });
+        if found != Some(wanted) {
+            anyhow::bail!("{} does not export the {} `{}`", module_path.display(), wanted, name);
+        }
+    }
+    for import in module.imports() {
+        if import.module() != FORGE_MODULE && import.module() != WASI_MODULE {
+            anyhow::bail!("{} imports {}::{}, which renderers cannot use", module_path.display(), import.module(), import.name());
+        }
+    }
+    Ok(())
+}
+
+/// Render `template` with the module at `module_path`.
+///
+/// The module runs in-process with no filesystem, network or environment
+/// access, and with bounded memory and fuel. It must export `memory`,
+/// `forge_alloc(len) -> ptr` and `forge_render(template_ptr, template_len,
+/// variables_ptr, variables_len) -> status`, and writes its result through the
+/// imported `forge.output(ptr, len)` and `forge.diagnostic(level, ptr, len)`.
+pub fn render_with_module(module_path: &Path, template: &[u8], variables: &Value) -> Result<WasmRenderOutput> {
+    render_with_fuel(module_path, template, variables, FUEL_PER_RENDER)
+}
+
+fn render_with_fuel(module_path: &Path, template: &[u8], variables: &Value, fuel: u64) -> Result<WasmRenderOutput> {
+    let engine = engine();
+    let module = load_module(&engine, module_path)?;
+    let linker = linker(&engine, &module)?;
+
+    let state = HostState {
+        output: Vec::new(),
+        diagnostics: Vec::new(),
+        diagnostic_bytes: 0,
+        limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).build(),
+    };
+    let mut store = Store::new(&engine, state);
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(fuel).map_err(|err| anyhow::anyhow!("{}", err))?;
+
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)
+        .with_context(|| format!("Failed to instantiate {}", module_path.display()))?;
+    let memory = instance.get_memory(&store, "memory")
+        .with_context(|| format!("{} does not export `memory`", module_path.display()))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&store, "forge_alloc")
+        .with_context(|| format!("{} does not export `forge_alloc(len: i32) -> i32`", module_path.display()))?;
+    let render = instance.get_typed_func::<(i32, i32, i32, i32), i32>(&store, "forge_render")
+        .with_context(|| format!("{} does not export `forge_render(i32, i32, i32, i32) -> i32`", module_path.display()))?;
+
+    let vars_json = serde_json::to_vec(variables)?;
+    let mut pass = |bytes: &[u8]| -> Result<(i32, i32)> {
+        let len = i32::try_from(bytes.len()).context("Input too large for a WASM renderer")?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, bytes)
+            .map_err(|err| anyhow::anyhow!("forge_alloc returned an invalid buffer: {}", err))?;
+        Ok((ptr, len))
+    };
+    let (template_ptr, template_len) = pass(template)?;
+    let (vars_ptr, vars_len) = pass(&vars_json)?;
+
+    let status = render.call(&mut store, (template_ptr, template_len, vars_ptr, vars_len));
+    let state = store.into_data();
+    let result = WasmRenderOutput { output: state.output, diagnostics: state.diagnostics };
+    match status {
+        Ok(0) => Ok(result),
+        Ok(code) => Err(render_error(format!("forge_render returned {}", code), &result.diagnostics)),
+        Err(err) => {
+            let reason = match err.as_trap_code() {
+                Some(wasmi::core::TrapCode::OutOfFuel) => "ran out of fuel (too much computation)".to_string(),
+                _ => err.to_string(),
+            };
+            Err(render_error(reason, &result.diagnostics))
+        }
+    }
+}
+
+fn render_error(reason: String, diagnostics: &[Diagnostic]) -> anyhow::Error {
+    let mut message = format!("WASM renderer failed: {}", reason);
+    for diagnostic in diagnostics {
+        message.push_str(&format!("\n  {}: {}", diagnostic.level.label(), diagnostic.message));
+    }
+    anyhow::anyhow!(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    /// A renderer whose `forge_render` runs `body`, with the template at
+    /// local 0 (length at local 1) and "note\n" at address 16; iovec data
+    /// goes from address 32.
+    fn module(dir: &TempDir, body: &str) -> std::path::PathBuf {
+        let wat = format!(r#"
+            (module
+              (import "forge" "output" (func $output (param i32 i32)))
+              (import "forge" "diagnostic" (func $diagnostic (param i32 i32 i32)))
+              (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (global $next (mut i32) (i32.const 1024))
+              (data (i32.const 16) "note\n")
+              (func (export "forge_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+              (func (export "forge_render") (param i32 i32 i32 i32) (result i32)
+                {}))"#, body);
+        let path = dir.path().join("renderer.wasm");
+        fs::write(&path, wat::parse_str(wat).unwrap()).unwrap();
+        path
+    }
+
+    fn render(body: &str) -> Result<WasmRenderOutput> {
+        let dir = TempDir::new().unwrap();
+        render_with_module(&module(&dir, body), b"Hello {{ name }}", &json!({ "name": "forge" }))
+    }
+
+    /// `fd_write(1, 32, iovs_len, 48)` after storing one iovec `{ ptr, len }` at
+    /// 32, then success.
+    fn fd_write(ptr: i64, len: i64, iovs_len: i64) -> String {
+        format!("(i32.store (i32.const 32) (i32.const {})) (i32.store (i32.const 36) (i32.const {}))
+                 (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const {}) (i32.const 48)))
+                 (i32.const 0)", ptr, len, iovs_len)
+    }
+
+    #[test]
+    fn renders_output_and_diagnostics() {
+        let rendered = render(&format!("(call $output (local.get 0) (local.get 1))
+                                        (call $diagnostic (i32.const 1) (i32.const 16) (i32.const 4))
+                                        {}", fd_write(16, 5, 1))).unwrap();
+        assert_eq!(rendered.output, b"Hello {{ name }}");
+        let diagnostics: Vec<_> = rendered.diagnostics.iter().map(|d| (d.level, d.message.as_str())).collect();
+        assert_eq!(diagnostics, [(DiagnosticLevel::Warning, "note"), (DiagnosticLevel::Info, "note")]);
+    }
+
+    #[test]
+    fn nonzero_status_fails_with_diagnostics() {
+        let err = render("(call $diagnostic (i32.const 2) (i32.const 16) (i32.const 4)) (i32.const 3)").unwrap_err();
+        assert_eq!(err.to_string(), "WASM renderer failed: forge_render returned 3\n  error: note");
+    }
+
+    #[test]
+    fn endless_loop_runs_out_of_fuel() {
+        let dir = TempDir::new().unwrap();
+        let module = module(&dir, "(loop $forever (br $forever)) (i32.const 0)");
+        let err = render_with_fuel(&module, b"", &json!({}), 1_000_000).unwrap_err();
+        assert!(err.to_string().contains("ran out of fuel"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_iovecs_outside_memory() {
+        for (body, expected) in [
+            (fd_write(65530, 16, 1), "outside the module's memory"),
+            (fd_write(-1, 2, 1), "outside the module's memory"),
+            (fd_write(16, 5, -1), "negative length"),
+            (fd_write(16, 5, 100_000), "at most 1024"),
+        ] {
+            let err = render(&body).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}: {}", expected, err);
+        }
+    }
+
+    #[test]
+    fn rejects_iovec_list_overflowing_the_address_space() {
+        let err = render("(drop (call $fd_write (i32.const 1) (i32.const -8) (i32.const 2) (i32.const 48))) (i32.const 0)").unwrap_err();
+        assert!(err.to_string().contains("outside the module's memory"), "{}", err);
+
+        // The second iovec's address, 0x7fff_fff8 + 8, overflows i32
+        let err = render("(drop (call $fd_write (i32.const 1) (i32.const 0x7ffffff8) (i32.const 2) (i32.const 48))) (i32.const 0)").unwrap_err();
+        assert!(err.to_string().contains("outside the module's memory"), "{}", err);
+    }
+
+    #[test]
+    fn caps_output() {
+        let err = render("(loop $more (call $output (i32.const 0) (i32.const 65536)) (br $more)) (i32.const 0)").unwrap_err();
+        assert!(err.to_string().contains("more than 67108864 bytes of output"), "{}", err);
+    }
+}